@@ -0,0 +1,65 @@
+// Timing the spatial hash against the old everything-against-everything check.
+// Run with `cargo bench --bench broadphase`.
+use std::time::Instant;
+
+use cmps_455_3_asteroids::{square_distance, Rng, SpatialHash};
+
+const AREA_PER_BODY: f32 = 64.0; // Keeping the crowding about the same as the field grows.
+const CELL_SIZE: f32 = 4.0; // Twice the biggest radius.
+const ROUNDS: u32 = 10;
+
+fn main() {
+    for count in [250, 1000, 2000, 4000, 8000, 16000] {
+        let mut rng = Rng::new(count as u64);
+        let width = (count as f32 * AREA_PER_BODY * 16.0 / 9.0).sqrt();
+        let (min, max) = ((0.0, 0.0), (width, width * 9.0 / 16.0));
+        let bodies = (0..count)
+            .map(|_| {
+                (
+                    rng.gen_range(min.0, max.0),
+                    rng.gen_range(min.1, max.1),
+                    rng.gen_range(0.5, 2.0),
+                )
+            })
+            .collect::<Vec<_>>();
+        let touching = |a: &(f32, f32, f32), b: &(f32, f32, f32)| {
+            square_distance(a.0, a.1, b.0, b.1) < a.2.powf(2.0) + b.2.powf(2.0)
+        };
+
+        // Everything against everything.
+        let start = Instant::now();
+        let mut naive_hits = 0;
+        for _round in 0..ROUNDS {
+            naive_hits = 0;
+            for first in 0..bodies.len() {
+                for second in first + 1..bodies.len() {
+                    naive_hits += touching(&bodies[first], &bodies[second]) as u32;
+                }
+            }
+        }
+        let naive_time = start.elapsed() / ROUNDS;
+
+        // The grid, rebuilt every round like it is every tick.
+        let mut grid = SpatialHash::new(CELL_SIZE, min, max);
+        let start = Instant::now();
+        let mut grid_hits = 0;
+        for _round in 0..ROUNDS {
+            grid.clear();
+            for (index, body) in bodies.iter().enumerate() {
+                grid.insert(index, (body.0, body.1), body.2);
+            }
+            grid_hits = grid
+                .candidate_pairs()
+                .into_iter()
+                .filter(|&(first, second)| touching(&bodies[first], &bodies[second]))
+                .count() as u32;
+        }
+        let grid_time = start.elapsed() / ROUNDS;
+
+        assert_eq!(naive_hits, grid_hits, "the grid missed something");
+        println!(
+            "{:>5} bodies: naive {:>10.3?}, grid {:>10.3?}, {} hits",
+            count, naive_time, grid_time, grid_hits
+        );
+    }
+}
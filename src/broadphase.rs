@@ -0,0 +1,134 @@
+// A uniform grid for finding out what might be touching what, without checking everything against everything.
+// The grid wraps around at the edges, same as the playfield, so things near opposite sides still meet.
+#[derive(Debug, Clone)]
+pub struct SpatialHash {
+    cell_size: (f32, f32),
+    origin: (f32, f32),
+    columns: usize,
+    rows: usize,
+    cells: Vec<Vec<usize>>,
+    spans_cells: Vec<bool>, // Whether each index landed in more than one cell.
+}
+
+impl SpatialHash {
+    // A grid covering min..max, split into cells of at least cell_size.
+    // Cells get stretched a little so a whole number of them fit, otherwise wrapping wouldn't line up.
+    pub fn new(cell_size: f32, min: (f32, f32), max: (f32, f32)) -> Self {
+        let columns = (((max.0 - min.0) / cell_size).floor() as usize).max(1);
+        let rows = (((max.1 - min.1) / cell_size).floor() as usize).max(1);
+        Self {
+            cell_size: (
+                (max.0 - min.0) / columns as f32,
+                (max.1 - min.1) / rows as f32,
+            ),
+            origin: min,
+            columns,
+            rows,
+            cells: vec![Vec::new(); columns * rows],
+            spans_cells: Vec::new(),
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.cells.iter_mut().for_each(Vec::clear);
+        self.spans_cells.clear();
+    }
+
+    // Dropping a circle into every cell it overlaps, wrapping cells that fall off the edge.
+    pub fn insert(&mut self, index: usize, position: (f32, f32), radius: f32) {
+        let cell = |value: f32, origin: f32, size: f32| ((value - origin) / size).floor() as i64;
+        let (min_column, max_column) = (
+            cell(position.0 - radius, self.origin.0, self.cell_size.0),
+            cell(position.0 + radius, self.origin.0, self.cell_size.0),
+        );
+        let (min_row, max_row) = (
+            cell(position.1 - radius, self.origin.1, self.cell_size.1),
+            cell(position.1 + radius, self.origin.1, self.cell_size.1),
+        );
+        // Anything bigger than the whole grid just goes everywhere once.
+        let column_span = (max_column - min_column + 1).min(self.columns as i64);
+        let row_span = (max_row - min_row + 1).min(self.rows as i64);
+        if self.spans_cells.len() <= index {
+            self.spans_cells.resize(index + 1, false);
+        }
+        self.spans_cells[index] = column_span * row_span > 1;
+        for row in min_row..min_row + row_span {
+            for column in min_column..min_column + column_span {
+                let wrapped_column = column.rem_euclid(self.columns as i64) as usize;
+                let wrapped_row = row.rem_euclid(self.rows as i64) as usize;
+                self.cells[wrapped_row * self.columns + wrapped_column].push(index);
+            }
+        }
+    }
+
+    // Every pair that shares a cell, each one only once, with the lower index first.
+    pub fn candidate_pairs(&self) -> Vec<(usize, usize)> {
+        let mut pairs = Vec::new();
+        // Only pairs where both sides span several cells can turn up twice, so only they need deduping.
+        let mut shared_pairs = Vec::new();
+        for cell in &self.cells {
+            for (offset, &first) in cell.iter().enumerate() {
+                for &second in &cell[offset + 1..] {
+                    let pair = (first.min(second), first.max(second));
+                    if first == second {
+                        continue;
+                    } else if self.spans_cells[first] && self.spans_cells[second] {
+                        shared_pairs.push(pair);
+                    } else {
+                        pairs.push(pair);
+                    }
+                }
+            }
+        }
+        shared_pairs.sort_unstable();
+        shared_pairs.dedup();
+        pairs.extend(shared_pairs);
+        pairs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::playfield::Playfield;
+    use crate::rng::Rng;
+
+    #[test]
+    fn finds_every_pair_brute_force_does() {
+        let playfield = Playfield::new((0.0, 0.0), (200.0, 120.0));
+        let mut rng = Rng::new(7);
+        let circles = (0..300)
+            .map(|index| {
+                // A few huge ones, to cover anything spanning the whole grid.
+                let radius = if index % 50 == 0 {
+                    rng.gen_range(60.0, 150.0)
+                } else {
+                    rng.gen_range(0.5, 12.0)
+                };
+                (
+                    (rng.gen_range(0.0, 200.0), rng.gen_range(0.0, 120.0)),
+                    radius,
+                )
+            })
+            .collect::<Vec<_>>();
+        let mut grid = SpatialHash::new(16.0, playfield.min, playfield.max);
+        for (index, (position, radius)) in circles.iter().enumerate() {
+            grid.insert(index, *position, *radius);
+        }
+        let mut pairs = grid.candidate_pairs();
+        let found = pairs.len();
+        pairs.sort_unstable();
+        pairs.dedup();
+        assert_eq!(pairs.len(), found, "pairs should only turn up once");
+
+        for first in 0..circles.len() {
+            for second in first + 1..circles.len() {
+                let ((a, a_radius), (b, b_radius)) = (circles[first], circles[second]);
+                let reach = a_radius + b_radius;
+                if playfield.distance_squared(a, b) < reach * reach {
+                    assert!(pairs.binary_search(&(first, second)).is_ok());
+                }
+            }
+        }
+    }
+}
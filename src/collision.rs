@@ -0,0 +1,628 @@
+use hecs::*;
+use macroquad::prelude::*;
+
+use crate::combo::Combo;
+use crate::components::{self, CollidableType};
+use crate::game::{Game, Sound};
+use crate::respawn;
+use crate::shield;
+use crate::{deg2rad, rad2deg, rotate_point};
+
+const ASTEROID_RESTITUTION: f32 = 0.9; // How much bounce is left after rocks hit, 1 being all of it.
+const ASTEROID_FRICTION: f32 = 0.2; // How much of the sliding past each other turns into spin.
+const ASTEROID_CORRECTION: f32 = 0.8; // How much of any overlap gets pushed apart each tick.
+const ASTEROID_SLOP: f32 = 0.01; // Overlap we're happy to leave, so resting rocks don't jitter.
+pub const EXTRA_LIFE_FLASH: f32 = 2.0; // How long the HUD makes a fuss about a life being handed out.
+
+// Something touched something else this tick. Systems below each pick out the ones they care about.
+#[derive(Debug, Clone, Copy)]
+pub struct CollisionEvent {
+    pub a: Entity,
+    pub b: Entity,
+    pub kind_a: CollidableType,
+    pub kind_b: CollidableType,
+    pub point: (f32, f32), // Roughly where they touched.
+}
+
+// One side of an event: who we are, what we are, who hit us and what they were.
+pub type CollisionSide = (Entity, CollidableType, Entity, CollidableType);
+
+impl CollisionEvent {
+    // Both ways round, so systems only ever have to think about one thing being hit by another.
+    pub fn sides(&self) -> [CollisionSide; 2] {
+        [
+            (self.a, self.kind_a, self.b, self.kind_b),
+            (self.b, self.kind_b, self.a, self.kind_a),
+        ]
+    }
+}
+
+impl Game {
+    pub fn collision_sides(&self) -> Vec<CollisionSide> {
+        self.collision_events
+            .iter()
+            .flat_map(CollisionEvent::sides)
+            .collect()
+    }
+
+    pub fn position_of(&self, entity: Entity) -> Option<components::Position> {
+        self.world
+            .get::<&components::Position>(entity)
+            .ok()
+            .map(|position| *position)
+    }
+
+    // Everything the bounce needs to know about one body, copied out so two can be worked on at once.
+    // A raised shield counts as a body the size of the bubble.
+    fn body_of(
+        &self,
+        entity: Entity,
+    ) -> Option<(
+        components::Position,
+        components::Velocity,
+        components::Collidable,
+    )> {
+        let mut query = self
+            .world
+            .query_one::<(
+                &components::Position,
+                &components::Velocity,
+                &components::Collidable,
+            )>(entity)
+            .ok()?;
+        let (position, velocity, collidable) = query.get()?;
+        let radius = if self.shielded(entity) {
+            shield::SHIELD_RADIUS
+        } else {
+            collidable.0
+        };
+        Some((
+            *position,
+            *velocity,
+            components::Collidable(radius, collidable.1),
+        ))
+    }
+
+    // The player dying, whatever it was that did it. They come back after a bit if there's lives left.
+    pub fn lose_life(&mut self, player: Entity) {
+        // Already dead this tick, getting hit twice doesn't count.
+        if self.world.get::<&components::Respawning>(player).is_ok() {
+            return;
+        }
+        self.lives = self.lives.saturating_sub(1);
+        self.combo = Combo::default();
+        self.hitstun += (64 / (1 + self.lives)) as f32 / 60.0;
+        self.clear_screen = self.difficulty.mercy;
+        if self.lives == 0 {
+            self.entities_to_destroy.push(player);
+            // explode.
+        } else {
+            let _ = self
+                .world
+                .insert_one(player, components::Respawning(respawn::RESPAWN_DELAY));
+        }
+    }
+
+    // The layers something's on, going by its kind if it doesn't say.
+    pub fn layers_of(&self, entity: Entity) -> components::CollisionLayers {
+        if let Ok(layers) = self.world.get::<&components::CollisionLayers>(entity) {
+            return *layers;
+        }
+        self.world
+            .get::<&components::Collidable>(entity)
+            .map(|collidable| components::CollisionLayers::default_for(collidable.1))
+            .unwrap_or(components::CollisionLayers(0, 0))
+    }
+
+    // The layers something hurts, same again.
+    pub fn damages_of(&self, entity: Entity) -> u32 {
+        if let Ok(damages) = self.world.get::<&components::Damages>(entity) {
+            return damages.0;
+        }
+        self.world
+            .get::<&components::Collidable>(entity)
+            .map(|collidable| components::Damages::default_for(collidable.1).0)
+            .unwrap_or(0)
+    }
+
+    // Whether touching one thing hurts the other.
+    pub fn hurts(&self, attacker: Entity, target: Entity) -> bool {
+        self.damages_of(attacker) & self.layers_of(target).0 != 0
+    }
+
+    // Losing lives, and breaking whatever got hit by something that breaks it.
+    pub fn damage_system(&mut self) {
+        for (this, _this_kind, other, _other_kind) in self.collision_sides() {
+            if self.hurts(other, this) {
+                self.hurt(this);
+            }
+        }
+    }
+
+    // Something getting hurt, whatever it is. Ships lose a life, or shield, and everything else knocks
+    // a hit off its health, breaking if that was its last. Hands back whether it broke.
+    pub fn hurt(&mut self, entity: Entity) -> bool {
+        if self.world.get::<&components::Controllable>(entity).is_ok() {
+            if self.shielded(entity) {
+                self.shield_hit(entity);
+            } else {
+                self.lose_life(entity);
+            }
+            return false;
+        }
+        if self.entities_to_destroy.contains(&entity) {
+            return true;
+        }
+        if let Ok(mut health) = self.world.get::<&mut components::Health>(entity) {
+            health.0 = health.0.saturating_sub(1);
+            if health.0 > 0 {
+                return false;
+            }
+        }
+        self.entities_to_destroy.push(entity);
+        true
+    }
+
+    // Whether a bullet came from something the player's in control of, since saucers shoot rocks too.
+    pub fn fired_by_player(&self, bullet: Entity) -> bool {
+        self.world
+            .get::<&components::Owner>(bullet)
+            .is_ok_and(|owner| self.world.get::<&components::Controllable>(owner.0).is_ok())
+    }
+
+    // Scooore!
+    pub fn scoring_system(&mut self) {
+        for (this, this_kind, other, other_kind) in self.collision_sides() {
+            if other_kind != CollidableType::BULLET || !self.fired_by_player(other) {
+                continue;
+            }
+            // Still a hit, but armoured rocks only pay out once they break.
+            if this_kind == CollidableType::ASTEROID && !self.entities_to_destroy.contains(&this) {
+                self.stats.hits += 1;
+                self.combo_hit();
+                continue;
+            }
+            let Some(points) = self.points_for(this, this_kind) else {
+                continue;
+            };
+            self.stats.hits += 1;
+            let points = points * self.combo_hit();
+            self.add_score(points);
+            let position = self
+                .world
+                .get::<&components::Position>(this)
+                .map(|position| *position);
+            if let Ok(position) = position {
+                self.score_popup((position.0, position.1), points);
+            }
+        }
+    }
+
+    // What shooting something's worth, going by the difficulty's point table.
+    pub fn points_for(&self, entity: Entity, kind: CollidableType) -> Option<u32> {
+        let difficulty = &self.difficulty;
+        match kind {
+            CollidableType::ASTEROID => {
+                let size = self.world.get::<&components::Collidable>(entity).ok()?.0;
+                Some(if size <= self.setting(difficulty.min_split_size) {
+                    difficulty.small_asteroid_points
+                } else if size >= self.setting(difficulty.asteroid_size) {
+                    difficulty.large_asteroid_points
+                } else {
+                    difficulty.medium_asteroid_points
+                })
+            }
+            CollidableType::SAUCER => Some(
+                match self.world.get::<&components::Saucer>(entity).ok()?.0 {
+                    components::SaucerSize::Large => difficulty.large_saucer_points,
+                    components::SaucerSize::Small => difficulty.small_saucer_points,
+                },
+            ),
+            _ => None,
+        }
+    }
+
+    // Points, and a life back for every threshold they carried us over.
+    pub fn add_score(&mut self, points: u32) {
+        let before = self.score;
+        self.score += points;
+        self.high_score = self.high_score.max(self.score);
+
+        let every = self.difficulty.extra_life_every;
+        if every == 0 || self.lives == 0 {
+            return;
+        }
+        let earned = self.score / every - before / every;
+        if earned > 0 && self.lives < self.difficulty.max_lives {
+            self.lives = (self.lives + earned).min(self.difficulty.max_lives);
+            self.extra_life_flash = EXTRA_LIFE_FLASH;
+            self.sounds.push(Sound::ExtraLife);
+        }
+    }
+
+    pub fn audio_system(&mut self) {
+        for (this, _this_kind, other, _other_kind) in self.collision_sides() {
+            if !self.hurts(other, this) {
+                continue;
+            }
+            if self.world.get::<&components::Controllable>(this).is_ok() {
+                self.sounds.push(if self.shielded(this) {
+                    Sound::ShieldHit
+                } else if self.layers_of(other).0 & components::LAYER_BULLET != 0 {
+                    Sound::PlayerDeathLaser
+                } else {
+                    Sound::PlayerDeathRock
+                });
+            } else if let Some(sound) = self
+                .world
+                .get::<&components::HitEffect>(this)
+                .ok()
+                .and_then(|effect| effect.0)
+            {
+                self.sounds.push(sound);
+            }
+        }
+    }
+
+    // All the little bits flying off things.
+    pub fn vfx_system(&mut self) {
+        for (this, _this_kind, other, _other_kind) in self.collision_sides() {
+            if !self.hurts(other, this) {
+                continue;
+            }
+            let Some(position) = self.position_of(this) else {
+                continue;
+            };
+            if self.world.get::<&components::Controllable>(this).is_err() {
+                let effect = self.world.get::<&components::HitEffect>(this).map(|e| *e);
+                if let Ok(effect) = effect {
+                    self.debris((position.0, position.1), effect, effect.3);
+                }
+                continue;
+            }
+            if self.shielded(this) {
+                // Something bouncing off the shield.
+                self.particles.create_particle(
+                    8,
+                    (position.0, position.1),
+                    (0.0, 0.0),
+                    0.9,
+                    0.5,
+                    components::PowerUpKind::Shield.color(),
+                    0.3,
+                    (shield::SHIELD_RADIUS / 2.0, shield::SHIELD_RADIUS / 2.0),
+                    (20.0, 20.0),
+                    0.25,
+                    0.1,
+                );
+                continue;
+            }
+            // We were shot, or flew into something.
+            let color = if self.layers_of(other).0 & components::LAYER_BULLET != 0 {
+                Color {
+                    r: 0.9,
+                    g: 0.1,
+                    b: 0.1,
+                    a: 1.0,
+                }
+            } else {
+                Color {
+                    r: 0.9,
+                    g: 0.9,
+                    b: 0.9,
+                    a: 1.0,
+                }
+            };
+            self.particles.create_particle(
+                16,
+                (position.0, position.1),
+                (0.0, 0.0),
+                0.95,
+                1.0,
+                color,
+                2.0,
+                (0.0, 0.0),
+                (50.0, 50.0),
+                0.5,
+                0.2,
+            );
+        }
+    }
+
+    // What flies off something that got hurt, or cleared away.
+    pub fn debris(&mut self, point: (f32, f32), effect: components::HitEffect, spread: f32) {
+        self.particles.create_particle(
+            effect.2,
+            point,
+            (0.0, 0.0),
+            0.95,
+            0.5,
+            effect.1,
+            0.4,
+            (0.0, 0.0),
+            (spread, spread),
+            0.25,
+            0.1,
+        );
+    }
+
+    // Big rocks break into smaller rocks.
+    pub fn splitting_system(&mut self) {
+        for (this, _this_kind, other, _other_kind) in self.collision_sides() {
+            if self.layers_of(this).0 & components::LAYER_ASTEROID != 0
+                && self.hurts(other, this)
+                && self.entities_to_destroy.contains(&this)
+            {
+                self.split_asteroid(this);
+            }
+        }
+    }
+
+    // Spreading the pieces evenly around where it was, two unless it says otherwise.
+    pub fn split_asteroid(&mut self, rock: Entity) {
+        let (Some(position), Ok(collidable)) = (
+            self.position_of(rock),
+            self.world.get::<&components::Collidable>(rock).map(|c| *c),
+        ) else {
+            return;
+        };
+        let pieces = self
+            .world
+            .get::<&components::Splits>(rock)
+            .map_or(2, |splits| splits.0);
+        let split_step = self.setting(self.difficulty.split_step);
+        if pieces == 0 || collidable.0 <= self.setting(self.difficulty.min_split_size) {
+            return;
+        }
+        let angle = (self.rng.rand() % 360) as f32;
+        for piece in 0..pieces {
+            let new_vector = rotate_point(
+                (collidable.0, 0.0),
+                angle + piece as f32 * 360.0 / pieces as f32,
+            );
+            self.asteroids_to_create.push((
+                position.0 + new_vector.0,
+                position.1 + new_vector.1,
+                collidable.0 - split_step,
+            ));
+        }
+    }
+
+    // Rocks pushing off each other, trading momentum and a bit of spin.
+    pub fn bounce_system(&mut self) {
+        let events = self.collision_events.clone();
+        for event in events {
+            // Rocks off each other, and off a raised shield.
+            let bounces = match (event.kind_a, event.kind_b) {
+                (CollidableType::ASTEROID, CollidableType::ASTEROID) => true,
+                (CollidableType::PLAYER, CollidableType::ASTEROID) => self.shielded(event.a),
+                (CollidableType::ASTEROID, CollidableType::PLAYER) => self.shielded(event.b),
+                _ => false,
+            };
+            if !bounces {
+                continue;
+            }
+            let (Some(mut first), Some(mut second)) =
+                (self.body_of(event.a), self.body_of(event.b))
+            else {
+                continue;
+            };
+            let (first_position, first_velocity, first_collidable) = &mut first;
+            let (second_position, second_velocity, second_collidable) = &mut second;
+
+            // Which way they're pushing, from the first to the second.
+            let delta = self.playfield.delta(
+                (first_position.0, first_position.1),
+                (second_position.0, second_position.1),
+            );
+            let distance = (delta.0 * delta.0 + delta.1 * delta.1).sqrt();
+            let normal = if distance > 0.0 {
+                (delta.0 / distance, delta.1 / distance)
+            } else {
+                (1.0, 0.0)
+            };
+            let tangent = (-normal.1, normal.0);
+            let (first_mass, second_mass) = (mass(first_collidable.0), mass(second_collidable.0));
+            let inverse_masses = 1.0 / first_mass + 1.0 / second_mass;
+
+            // Only bouncing things that are still heading into each other.
+            let relative = (
+                second_velocity.0 - first_velocity.0,
+                second_velocity.1 - first_velocity.1,
+            );
+            let closing = relative.0 * normal.0 + relative.1 * normal.1;
+            if closing < 0.0 {
+                let impulse = -(1.0 + ASTEROID_RESTITUTION) * closing / inverse_masses;
+                // Scraping past each other gets turned into spin. Whatever they're already spinning
+                // moves the surfaces at the contact too, so that counts towards the scrape.
+                let surfaces = deg2rad(first_velocity.2) * first_collidable.0
+                    + deg2rad(second_velocity.2) * second_collidable.0;
+                let sliding = relative.0 * tangent.0 + relative.1 * tangent.1 - surfaces;
+                let friction = -sliding * ASTEROID_FRICTION / inverse_masses;
+                let push = (
+                    normal.0 * impulse + tangent.0 * friction,
+                    normal.1 * impulse + tangent.1 * friction,
+                );
+                first_velocity.0 -= push.0 / first_mass;
+                first_velocity.1 -= push.1 / first_mass;
+                second_velocity.0 += push.0 / second_mass;
+                second_velocity.1 += push.1 / second_mass;
+                // Treating rocks as solid discs, so the spin is 2 * impulse / (mass * radius).
+                first_velocity.2 -= rad2deg(2.0 * friction / (first_mass * first_collidable.0));
+                second_velocity.2 -= rad2deg(2.0 * friction / (second_mass * second_collidable.0));
+            }
+
+            // Nudging them apart so they don't stay stuck inside each other.
+            let overlap = first_collidable.0 + second_collidable.0 - distance;
+            if overlap > ASTEROID_SLOP {
+                let correction = (overlap - ASTEROID_SLOP) * ASTEROID_CORRECTION / inverse_masses;
+                (first_position.0, first_position.1) = self.playfield.wrap((
+                    first_position.0 - normal.0 * correction / first_mass,
+                    first_position.1 - normal.1 * correction / first_mass,
+                ));
+                (second_position.0, second_position.1) = self.playfield.wrap((
+                    second_position.0 + normal.0 * correction / second_mass,
+                    second_position.1 + normal.1 * correction / second_mass,
+                ));
+            }
+
+            for (entity, (position, velocity, _)) in [(event.a, first), (event.b, second)] {
+                if let Ok(mut current) = self.world.get::<&mut components::Position>(entity) {
+                    *current = position;
+                }
+                if let Ok(mut current) = self.world.get::<&mut components::Velocity>(entity) {
+                    *current = velocity;
+                }
+            }
+        }
+    }
+}
+
+// Rocks are about as heavy as they are big.
+fn mass(radius: f32) -> f32 {
+    radius * radius
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rock(game: &mut Game, position: (f32, f32), velocity: (f32, f32, f32), size: f32) -> Entity {
+        game.world.spawn((
+            components::Position(position.0, position.1, 0.0),
+            components::Velocity(velocity.0, velocity.1, velocity.2),
+            components::Collidable(size, CollidableType::ASTEROID),
+        ))
+    }
+
+    fn bounce(game: &mut Game, a: Entity, b: Entity) {
+        game.collision_events.push(CollisionEvent {
+            a,
+            b,
+            kind_a: CollidableType::ASTEROID,
+            kind_b: CollidableType::ASTEROID,
+            point: (0.0, 0.0),
+        });
+        game.bounce_system();
+    }
+
+    fn velocity(game: &Game, entity: Entity) -> components::Velocity {
+        *game.world.get::<&components::Velocity>(entity).unwrap()
+    }
+
+    #[test]
+    fn bounces_keep_momentum() {
+        let mut game = Game::new(1);
+        let (a, b) = (
+            rock(&mut game, (100.0, 70.0), (6.0, 1.0, 30.0), 8.0),
+            rock(&mut game, (110.0, 74.0), (-3.0, -2.0, -10.0), 4.0),
+        );
+        let momentum = |game: &Game| {
+            [(a, 8.0), (b, 4.0)]
+                .iter()
+                .fold((0.0, 0.0), |total, (entity, size)| {
+                    let velocity = velocity(game, *entity);
+                    (
+                        total.0 + mass(*size) * velocity.0,
+                        total.1 + mass(*size) * velocity.1,
+                    )
+                })
+        };
+        let before = momentum(&game);
+        bounce(&mut game, a, b);
+        let after = momentum(&game);
+        assert!((before.0 - after.0).abs() < 1e-3 && (before.1 - after.1).abs() < 1e-3);
+        // And they're not still heading into each other.
+        assert!(velocity(&game, b).0 - velocity(&game, a).0 > 0.0);
+    }
+
+    #[test]
+    fn spin_drags_the_other_rock_round_like_gears() {
+        let mut game = Game::new(1);
+        let (a, b) = (
+            rock(&mut game, (100.0, 70.0), (1.0, 0.0, 90.0), 5.0),
+            rock(&mut game, (110.0, 70.0), (-1.0, 0.0, 0.0), 5.0),
+        );
+        bounce(&mut game, a, b);
+        let (first, second) = (velocity(&game, a), velocity(&game, b));
+        assert!(first.2 > 0.0 && first.2 < 90.0);
+        assert!(second.2 < 0.0);
+    }
+
+    #[test]
+    fn extra_lives_at_each_threshold_up_to_the_cap() {
+        let mut game = Game::new(1);
+        game.restart(1);
+        let every = game.difficulty.extra_life_every;
+        let lives = game.lives;
+        game.add_score(every - 1);
+        assert_eq!(game.lives, lives);
+        game.add_score(1);
+        assert_eq!(game.lives, lives + 1);
+        // Jumping over two thresholds at once pays out both, but never past the cap.
+        game.add_score(every * 2);
+        assert_eq!(game.lives, (lives + 3).min(game.difficulty.max_lives));
+        game.add_score(every * 100);
+        assert_eq!(game.lives, game.difficulty.max_lives);
+    }
+
+    #[test]
+    fn no_extra_lives_without_a_run() {
+        let mut game = Game::new(1);
+        game.add_score(game.difficulty.extra_life_every * 3);
+        assert_eq!(game.lives, 0);
+    }
+
+    #[test]
+    fn reactions_come_from_what_things_hurt() {
+        let mut game = Game::new(1);
+        let rock = crate::game::create_asteroid_point(
+            &mut game.world,
+            &mut game.rng,
+            (50.0, 50.0),
+            4.0,
+            0.0,
+        );
+        let bullet = crate::game::create_bullet(
+            &mut game.world,
+            rock,
+            (50.0, 50.0, 0.0),
+            (0.0, 0.0),
+            WHITE,
+            (1.0, 1.0),
+        );
+        // Something new that nothing's got an arm for, sharing the saucers' layer so bullets break it.
+        let mine = game.world.spawn((
+            components::Position(80.0, 50.0, 0.0),
+            components::Collidable(2.0, CollidableType::PICKUP),
+            components::CollisionLayers(components::LAYER_SAUCER, components::LAYER_BULLET),
+            components::Damages(components::LAYER_BULLET),
+        ));
+        let other_bullet = crate::game::create_bullet(
+            &mut game.world,
+            rock,
+            (80.0, 50.0, 0.0),
+            (0.0, 0.0),
+            WHITE,
+            (1.0, 1.0),
+        );
+        for (a, kind_a, b) in [
+            (rock, CollidableType::ASTEROID, bullet),
+            (mine, CollidableType::PICKUP, other_bullet),
+        ] {
+            game.collision_events.push(CollisionEvent {
+                a,
+                b,
+                kind_a,
+                kind_b: CollidableType::BULLET,
+                point: (0.0, 0.0),
+            });
+        }
+        game.damage_system();
+        game.audio_system();
+        for entity in [rock, bullet, mine, other_bullet] {
+            assert!(game.entities_to_destroy.contains(&entity));
+        }
+        assert_eq!(game.sounds, vec![Sound::AsteroidExplode]);
+    }
+}
@@ -0,0 +1,109 @@
+use crate::game::Game;
+
+pub const COMBO_WINDOW: f32 = 2.0; // Seconds after a hit to land the next one before the chain drops.
+const HITS_PER_STEP: u32 = 4; // How many hits in a row it takes to bump the multiplier up one.
+const MAX_MULTIPLIER: u32 = 8;
+
+// Hits landed one after another, without a miss or a scratch on the ship in between.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Combo {
+    pub chain: u32, // Hits so far.
+    pub timer: f32, // Seconds left to keep it going.
+}
+
+impl Combo {
+    pub fn multiplier(&self) -> u32 {
+        (1 + self.chain / HITS_PER_STEP).min(MAX_MULTIPLIER)
+    }
+
+    // How much of the window's left, from 1 just hit to 0 gone, for the HUD.
+    pub fn remaining(&self) -> f32 {
+        (self.timer / COMBO_WINDOW).clamp(0.0, 1.0)
+    }
+}
+
+// How the run went, for the game over screen.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct RunStats {
+    pub shots_fired: u32,
+    pub hits: u32,
+    pub misses: u32, // Shots that fizzled out without hitting anything.
+    pub best_chain: u32,
+    pub best_multiplier: u32,
+}
+
+impl RunStats {
+    // How many shots found something, out of 100.
+    pub fn accuracy(&self) -> f32 {
+        if self.shots_fired == 0 {
+            0.0
+        } else {
+            self.hits as f32 / self.shots_fired as f32 * 100.0
+        }
+    }
+}
+
+impl Game {
+    // Keeping the chain going, and handing back what the hit's points get multiplied by.
+    // Shots that land are counted by whoever's scoring them, since blasts keep the chain going too.
+    pub fn combo_hit(&mut self) -> u32 {
+        self.combo.chain += 1;
+        self.combo.timer = COMBO_WINDOW;
+        self.stats.best_chain = self.stats.best_chain.max(self.combo.chain);
+        self.stats.best_multiplier = self.stats.best_multiplier.max(self.combo.multiplier());
+        self.combo.multiplier()
+    }
+
+    // A shot went nowhere.
+    pub fn combo_miss(&mut self) {
+        self.stats.misses += 1;
+        self.combo = Combo::default();
+    }
+
+    // Letting the chain drop once the window's up.
+    pub fn combo_system(&mut self, delta_time: f32) {
+        if self.combo.chain == 0 {
+            return;
+        }
+        self.combo.timer -= delta_time;
+        if self.combo.timer <= 0.0 {
+            self.combo = Combo::default();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn multiplier_steps_up_every_few_hits_to_the_cap() {
+        let mut game = Game::new(1);
+        let multipliers = (0..HITS_PER_STEP * (MAX_MULTIPLIER + 2))
+            .map(|_| game.combo_hit())
+            .collect::<Vec<_>>();
+        assert_eq!(multipliers[0], 1);
+        assert_eq!(multipliers[HITS_PER_STEP as usize - 2], 1);
+        assert_eq!(multipliers[HITS_PER_STEP as usize - 1], 2);
+        assert_eq!(*multipliers.last().unwrap(), MAX_MULTIPLIER);
+        assert_eq!(game.stats.best_multiplier, MAX_MULTIPLIER);
+        assert_eq!(game.stats.best_chain, multipliers.len() as u32);
+    }
+
+    #[test]
+    fn misses_and_waiting_drop_the_chain() {
+        let mut game = Game::new(1);
+        game.combo_hit();
+        game.combo_hit();
+        game.combo_miss();
+        assert_eq!(game.combo.chain, 0);
+        assert_eq!(game.stats.misses, 1);
+        assert_eq!(game.stats.best_chain, 2);
+
+        game.combo_hit();
+        game.combo_system(COMBO_WINDOW * 0.5);
+        assert_eq!(game.combo.chain, 1);
+        game.combo_system(COMBO_WINDOW * 0.6);
+        assert_eq!(game.combo.chain, 0);
+    }
+}
@@ -1,21 +1,157 @@
 #[derive(Debug, Clone, Copy)]
 pub struct Position(pub f32, pub f32, pub f32); // Position stored in (x, y, degrees).
 
+#[derive(Debug, Clone, Copy)]
+pub struct LastPosition(pub f32, pub f32, pub f32); // Where the entity was on the previous tick, for interpolating.
+
 #[derive(Debug, Clone, Copy)]
 pub struct Velocity(pub f32, pub f32, pub f32); // Position stored in (x, y, degrees).
 
 #[derive(Debug, Clone)]
 pub struct Draw(pub macroquad::color::Color, pub Vec<(f32, f32)>); // Drawables are vectors consisting of four points, a (x1, y1) and (x2, y2). Draw lines based on these and the rotation.
 
-#[derive(Debug, Clone, Copy)]
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum CollidableType {
     PLAYER,
     ASTEROID,
     BULLET,
+    SAUCER,
+    PICKUP,
 }
 
 #[derive(Debug, Clone, Copy)]
 pub struct Collidable(pub f32, pub CollidableType); // Just storing the radius of the object's hitbox.
 
+// Layers for CollisionLayers. New kinds of things just need a bit of their own.
+pub const LAYER_PLAYER: u32 = 1 << 0;
+pub const LAYER_ASTEROID: u32 = 1 << 1;
+pub const LAYER_BULLET: u32 = 1 << 2;
+pub const LAYER_SAUCER: u32 = 1 << 3;
+pub const LAYER_PICKUP: u32 = 1 << 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CollisionLayers(pub u32, pub u32); // The layers this is on, and the layers it collides with.
+
+impl CollisionLayers {
+    // What each kind of thing gets if nobody says otherwise.
+    pub fn default_for(kind: CollidableType) -> Self {
+        match kind {
+            CollidableType::PLAYER => Self(
+                LAYER_PLAYER,
+                LAYER_ASTEROID | LAYER_BULLET | LAYER_SAUCER | LAYER_PICKUP,
+            ),
+            CollidableType::ASTEROID => Self(
+                LAYER_ASTEROID,
+                LAYER_PLAYER | LAYER_ASTEROID | LAYER_BULLET | LAYER_SAUCER,
+            ),
+            CollidableType::BULLET => {
+                Self(LAYER_BULLET, LAYER_PLAYER | LAYER_ASTEROID | LAYER_SAUCER)
+            }
+            CollidableType::SAUCER => {
+                Self(LAYER_SAUCER, LAYER_PLAYER | LAYER_ASTEROID | LAYER_BULLET)
+            }
+            // Only the player can pick things up, everything else flies right through.
+            CollidableType::PICKUP => Self(LAYER_PICKUP, LAYER_PLAYER),
+        }
+    }
+
+    // Both sides have to want it, so either one can opt out.
+    pub fn accepts(&self, other: &CollisionLayers) -> bool {
+        self.1 & other.0 != 0 && other.1 & self.0 != 0
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Damages(pub u32); // The layers this hurts by touching them, out of the ones it collides with.
+
+impl Damages {
+    pub fn default_for(kind: CollidableType) -> Self {
+        match kind {
+            // Ramming a saucer takes it down with us, and bullets break on the hull.
+            CollidableType::PLAYER => Self(LAYER_SAUCER | LAYER_BULLET),
+            CollidableType::ASTEROID => Self(LAYER_PLAYER | LAYER_SAUCER | LAYER_BULLET),
+            CollidableType::BULLET => Self(LAYER_PLAYER | LAYER_ASTEROID | LAYER_SAUCER),
+            CollidableType::SAUCER => Self(LAYER_PLAYER | LAYER_ASTEROID | LAYER_BULLET),
+            CollidableType::PICKUP => Self(0),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct HitEffect(
+    pub Option<crate::game::Sound>,
+    pub macroquad::color::Color,
+    pub i32,
+    pub f32,
+); // What getting hurt sounds like, and the debris: its colour, how many bits and how fast they fly.
+
+#[derive(Debug, Clone, Copy)]
+pub struct PreciseCollision(); // Use the Draw outline for collisions, once the radius says they're close.
+
 #[derive(Debug, Clone, Copy)]
 pub struct Controllable(); // For allowing this entity to be controlled.
+
+#[derive(Debug, Clone, Copy)]
+pub struct Hyperspace(pub f32, pub (f32, f32)); // Gone from the playfield, with seconds until it comes back and where.
+
+#[derive(Debug, Clone, Copy)]
+pub struct Respawning(pub f32); // Blown up and waiting to come back, with seconds until it can try.
+
+#[derive(Debug, Clone, Copy)]
+pub struct Invulnerable(pub f32); // Can't touch or be touched for this many more seconds.
+
+#[derive(Debug, Clone, Copy)]
+pub struct Health(pub u32); // Hits it can take, counting the one that finishes it. Anything without one goes in one.
+
+#[derive(Debug, Clone, Copy)]
+pub struct Splits(pub u32); // How many pieces it breaks into, for anything that doesn't break in two.
+
+#[derive(Debug, Clone, Copy)]
+pub struct Explodes(pub f32); // Goes off when it's destroyed, hitting everything within this radius.
+
+#[derive(Debug, Clone, Copy)]
+pub struct Magnetic(pub f32); // Pulled toward the player, accelerating this much a second.
+
+#[derive(Debug, Clone, Copy)]
+pub struct Shield(pub f32, pub bool); // Energy left, from 0 to 1, and whether it's up right now.
+
+#[derive(Debug, Clone, Copy)]
+pub struct Lifetime(pub f32, pub f32); // Seconds and distance left before it fizzles out, whichever runs out first.
+
+#[derive(Debug, Clone, Copy)]
+pub struct Owner(pub hecs::Entity, pub f32); // Whoever fired this, and how many more seconds it can't hit them.
+
+impl Owner {
+    pub fn ignores(&self, entity: hecs::Entity) -> bool {
+        self.0 == entity && self.1 > 0.0
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct FastMover(); // Checked along the whole path it moved this tick, so it can't skip through things.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SaucerSize {
+    Large, // Big, slow and shoots wherever.
+    Small, // Small, quick and aims.
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Saucer(pub SaucerSize, pub f32, pub f32); // Which one, seconds until it next fires, and how much further it flies before leaving.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PowerUpKind {
+    SpreadShot, // Three bullets a shot, fanned out.
+    RapidFire,  // More bullets out at once, and quicker ones.
+    Shield,     // Keeps the shield up without using any energy.
+    Bomb,       // Clears the field, right away.
+    ExtraLife,  // One more go, right away.
+    SlowTime,   // Everything but the player and their bullets at half speed.
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Pickup(pub PowerUpKind); // Floating about, waiting for the player to fly into it.
+
+#[derive(Debug, Clone, Default)]
+pub struct PowerUps(pub Vec<(PowerUpKind, f32)>); // What the ship's got going, with seconds left on each.
@@ -0,0 +1,384 @@
+use std::io::{Error, ErrorKind};
+use std::ops::RangeInclusive;
+use std::path::Path;
+
+use crate::game::Game;
+
+// What a setting grows with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CurveInput {
+    Wave,   // Every wave after the first.
+    Minute, // Every minute into the run.
+}
+
+impl CurveInput {
+    pub fn name(&self) -> &'static str {
+        match self {
+            CurveInput::Wave => "wave",
+            CurveInput::Minute => "minute",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<CurveInput> {
+        match name {
+            "wave" => Some(CurveInput::Wave),
+            "minute" => Some(CurveInput::Minute),
+            _ => None,
+        }
+    }
+}
+
+// A setting that starts somewhere and moves by step every wave or minute, never going past limit.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Curve {
+    pub start: f32,
+    pub step: f32,
+    pub limit: f32,
+    pub per: CurveInput,
+}
+
+impl Curve {
+    pub const fn flat(value: f32) -> Self {
+        Self::per_wave(value, 0.0, value)
+    }
+
+    pub const fn per_wave(start: f32, step: f32, limit: f32) -> Self {
+        Self {
+            start,
+            step,
+            limit,
+            per: CurveInput::Wave,
+        }
+    }
+
+    pub const fn per_minute(start: f32, step: f32, limit: f32) -> Self {
+        Self {
+            start,
+            step,
+            limit,
+            per: CurveInput::Minute,
+        }
+    }
+
+    // What it's at on a given wave, this far into the run.
+    pub fn at(&self, wave: u32, minutes: f32) -> f32 {
+        let steps = match self.per {
+            CurveInput::Wave => wave.saturating_sub(1) as f32,
+            CurveInput::Minute => minutes,
+        };
+        let value = self.start + self.step * steps;
+        if self.step >= 0.0 {
+            value.min(self.limit)
+        } else {
+            value.max(self.limit)
+        }
+    }
+
+    // Written as `start step limit per`, or just the one number if it never changes.
+    fn to_config_string(self) -> String {
+        if self.step == 0.0 && self.start == self.limit {
+            format!("{}", self.start)
+        } else {
+            format!(
+                "{} {} {} {}",
+                self.start,
+                self.step,
+                self.limit,
+                self.per.name()
+            )
+        }
+    }
+
+    fn parse(value: &str) -> Option<Self> {
+        let parts = value.split_whitespace().collect::<Vec<_>>();
+        let number = |index: usize| parts.get(index)?.parse::<f32>().ok();
+        match parts.len() {
+            1 => Some(Self::flat(number(0)?)),
+            3 => Some(Self::per_wave(number(0)?, number(1)?, number(2)?)),
+            4 => Some(Self {
+                start: number(0)?,
+                step: number(1)?,
+                limit: number(2)?,
+                per: CurveInput::from_name(parts[3])?,
+            }),
+            _ => None,
+        }
+    }
+}
+
+// How a run ramps up. Kept as data so new profiles are just a file, not a rebuild.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Difficulty {
+    pub name: String,
+    pub wave_asteroids: Curve,    // How many big rocks a wave starts with.
+    pub asteroid_size: Curve,     // How big they are.
+    pub asteroid_speed: Curve,    // The fastest they drift along each axis.
+    pub split_step: Curve,        // How much smaller each half is when one breaks.
+    pub min_split_size: Curve,    // Anything this size or smaller just breaks.
+    pub saucer_interval: Curve,   // Roughly how many seconds between saucers.
+    pub special_asteroids: Curve, // The odds of a wave's rock being one of the special kinds.
+    pub mercy: bool,              // Wiping the field whenever the player dies, like it used to.
+    pub extra_life_every: u32,    // A life back every this many points, or never if it's 0.
+    pub max_lives: u32,           // Extra lives stop piling up past this.
+
+    // What everything's worth. Smaller rocks are harder to hit, so they pay more.
+    pub large_asteroid_points: u32, // Rocks at the wave's starting size.
+    pub medium_asteroid_points: u32, // Anything between.
+    pub small_asteroid_points: u32, // Rocks too small to split any further.
+    pub large_saucer_points: u32,
+    pub small_saucer_points: u32,
+}
+
+impl Default for Difficulty {
+    fn default() -> Self {
+        Self::normal()
+    }
+}
+
+impl Difficulty {
+    pub fn easy() -> Self {
+        Self {
+            name: "Easy".to_string(),
+            wave_asteroids: Curve::per_wave(3.0, 1.0, 8.0),
+            asteroid_size: Curve::flat(4.0),
+            asteroid_speed: Curve::per_wave(7.0, 0.5, 12.0),
+            split_step: Curve::flat(1.0),
+            min_split_size: Curve::flat(3.0),
+            saucer_interval: Curve::flat(25.0),
+            special_asteroids: Curve::per_wave(0.0, 0.05, 0.3),
+            mercy: true,
+            extra_life_every: 7500,
+            max_lives: 5,
+            ..Self::normal()
+        }
+    }
+
+    pub fn normal() -> Self {
+        Self {
+            name: "Normal".to_string(),
+            wave_asteroids: Curve::per_wave(4.0, 2.0, 11.0),
+            asteroid_size: Curve::flat(5.0),
+            asteroid_speed: Curve::per_wave(10.0, 1.0, 20.0),
+            split_step: Curve::flat(1.0),
+            min_split_size: Curve::flat(3.0),
+            saucer_interval: Curve::flat(15.0),
+            special_asteroids: Curve::per_wave(0.0, 0.1, 0.5),
+            mercy: false,
+            extra_life_every: 10000,
+            max_lives: 5,
+            large_asteroid_points: 20,
+            medium_asteroid_points: 50,
+            small_asteroid_points: 100,
+            large_saucer_points: 200,
+            small_saucer_points: 1000,
+        }
+    }
+
+    pub fn hard() -> Self {
+        Self {
+            name: "Hard".to_string(),
+            wave_asteroids: Curve::per_wave(6.0, 2.0, 12.0),
+            asteroid_size: Curve::flat(5.0),
+            asteroid_speed: Curve::per_wave(13.0, 1.5, 26.0),
+            split_step: Curve::flat(1.0),
+            min_split_size: Curve::flat(3.0),
+            saucer_interval: Curve::per_minute(12.0, -1.0, 5.0),
+            special_asteroids: Curve::per_wave(0.1, 0.1, 0.7),
+            mercy: false,
+            extra_life_every: 15000,
+            max_lives: 4,
+            ..Self::normal()
+        }
+    }
+
+    pub fn presets() -> Vec<Difficulty> {
+        vec![Self::easy(), Self::normal(), Self::hard()]
+    }
+
+    // A preset by name, or failing that a profile file at that path.
+    pub fn from_arg(arg: &str) -> std::io::Result<Self> {
+        match Self::presets()
+            .into_iter()
+            .find(|preset| preset.name.eq_ignore_ascii_case(arg))
+        {
+            Some(preset) => Ok(preset),
+            None => Self::load(arg),
+        }
+    }
+
+    // Each with the range a file's allowed to set it to, so a typo can't freeze or flood the game.
+    fn curves_mut(&mut self) -> [(&'static str, &mut Curve, RangeInclusive<f32>); 7] {
+        [
+            ("wave_asteroids", &mut self.wave_asteroids, 1.0..=100.0),
+            ("asteroid_size", &mut self.asteroid_size, 0.5..=20.0),
+            ("asteroid_speed", &mut self.asteroid_speed, 0.0..=100.0),
+            ("split_step", &mut self.split_step, 0.1..=20.0),
+            ("min_split_size", &mut self.min_split_size, 0.0..=20.0),
+            ("saucer_interval", &mut self.saucer_interval, 1.0..=3600.0),
+            ("special_asteroids", &mut self.special_asteroids, 0.0..=1.0),
+        ]
+    }
+
+    fn counts_mut(&mut self) -> [(&'static str, &mut u32, u32); 7] {
+        [
+            ("extra_life_every", &mut self.extra_life_every, 10_000_000),
+            ("max_lives", &mut self.max_lives, 99),
+            (
+                "large_asteroid_points",
+                &mut self.large_asteroid_points,
+                1_000_000,
+            ),
+            (
+                "medium_asteroid_points",
+                &mut self.medium_asteroid_points,
+                1_000_000,
+            ),
+            (
+                "small_asteroid_points",
+                &mut self.small_asteroid_points,
+                1_000_000,
+            ),
+            (
+                "large_saucer_points",
+                &mut self.large_saucer_points,
+                1_000_000,
+            ),
+            (
+                "small_saucer_points",
+                &mut self.small_saucer_points,
+                1_000_000,
+            ),
+        ]
+    }
+
+    // Anything the file leaves out stays at Normal.
+    pub fn parse(config: &str) -> std::io::Result<Self> {
+        let mut difficulty = Self {
+            name: "Custom".to_string(),
+            ..Self::normal()
+        };
+        for (line_number, line) in config.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let invalid = |message: String| {
+                Error::new(
+                    ErrorKind::InvalidData,
+                    format!("line {}: {}", line_number + 1, message),
+                )
+            };
+            let (name, value) = line
+                .split_once('=')
+                .ok_or_else(|| invalid("expected `setting = value`".to_string()))?;
+            let (name, value) = (name.trim(), value.trim());
+            if name == "name" {
+                difficulty.name = value.to_string();
+                continue;
+            }
+            if name == "mercy" {
+                difficulty.mercy = value
+                    .parse()
+                    .map_err(|_| invalid(format!("expected `true` or `false`, got `{}`", value)))?;
+                continue;
+            }
+            if let Some((_, count, max)) = difficulty
+                .counts_mut()
+                .into_iter()
+                .find(|(setting, _, _)| *setting == name)
+            {
+                *count = value
+                    .parse()
+                    .map_err(|_| invalid(format!("expected a whole number, got `{}`", value)))?;
+                if *count > max {
+                    return Err(invalid(format!("`{}` can't be more than {}", name, max)));
+                }
+                continue;
+            }
+            let (_, curve, range) = difficulty
+                .curves_mut()
+                .into_iter()
+                .find(|(setting, _, _)| *setting == name)
+                .ok_or_else(|| invalid(format!("unknown setting `{}`", name)))?;
+            *curve = Curve::parse(value).ok_or_else(|| {
+                invalid(format!(
+                    "expected `value` or `start step limit [wave|minute]`, got `{}`",
+                    value
+                ))
+            })?;
+            // The curve never leaves the stretch between start and limit, so those two are all we need to check.
+            if !curve.step.is_finite()
+                || !range.contains(&curve.start)
+                || !range.contains(&curve.limit)
+            {
+                return Err(invalid(format!(
+                    "`{}` has to stay between {} and {}",
+                    name,
+                    range.start(),
+                    range.end()
+                )));
+            }
+        }
+        Ok(difficulty)
+    }
+
+    pub fn to_config_string(&self) -> String {
+        let mut config = format!("name = {}\nmercy = {}\n", self.name, self.mercy);
+        for (name, count, _) in self.clone().counts_mut() {
+            config.push_str(&format!("{} = {}\n", name, count));
+        }
+        for (name, curve, _) in self.clone().curves_mut() {
+            config.push_str(&format!("{} = {}\n", name, curve.to_config_string()));
+        }
+        config
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        Self::parse(&std::fs::read_to_string(path)?)
+    }
+}
+
+impl Game {
+    // Where one of the difficulty's settings is at, right now.
+    pub fn setting(&self, curve: Curve) -> f32 {
+        curve.at(self.wave, self.time as f32 / 60.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn presets_round_trip() {
+        for preset in Difficulty::presets() {
+            assert_eq!(
+                Difficulty::parse(&preset.to_config_string()).unwrap(),
+                preset
+            );
+        }
+    }
+
+    #[test]
+    fn bad_lines_say_where() {
+        let error = Difficulty::parse("max_lives = 3\nwave_asteroids = lots\n").unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+        assert!(error.to_string().starts_with("line 2:"));
+        assert!(Difficulty::parse("nonsense = 1").is_err());
+
+        for line in [
+            "wave_asteroids = 1e12",
+            "wave_asteroids = 4 2 inf",
+            "asteroid_speed = NaN",
+            "split_step = 0",
+            "split_step = 1 -1 -5",
+            "saucer_interval = 15 inf 20 minute",
+            "special_asteroids = 2",
+            "max_lives = 4000000000",
+        ] {
+            let error = Difficulty::parse(&format!("name = Broken\n{}\n", line)).unwrap_err();
+            assert_eq!(error.kind(), ErrorKind::InvalidData, "{}", line);
+            assert!(error.to_string().starts_with("line 2:"), "{}", line);
+        }
+    }
+}
@@ -0,0 +1,1195 @@
+use hecs::*;
+use macroquad::prelude::*;
+
+use crate::broadphase::SpatialHash;
+use crate::collision::CollisionEvent;
+use crate::combo::{Combo, RunStats};
+use crate::components::{self, PowerUpKind};
+use crate::difficulty::Difficulty;
+use crate::narrowphase;
+use crate::particles::ParticleStorage;
+use crate::playfield::Playfield;
+use crate::popup::ScorePopup;
+use crate::powerup::SPREAD_ANGLE;
+use crate::rng::{self, Rng};
+use crate::rotate_point;
+use crate::saucer;
+use crate::shield;
+use crate::wave;
+
+// Bump this with anything that changes how a run plays out, so replays from before know they won't match.
+pub const SIMULATION_VERSION: u32 = 9;
+const BROADPHASE_CELL_SIZE: f32 = 16.0; // Comfortably bigger than the biggest rock.
+pub const DEFAULT_BULLET_LIFETIME: f32 = 4.0; // Seconds.
+pub const DEFAULT_BULLET_RANGE: f32 = 75.0; // A bit short of going all the way round top to bottom.
+pub const DEFAULT_MAX_BULLETS: usize = 4; // Per ship, like the arcade.
+const BULLET_SPEED: f32 = 20.0; // On top of however fast the ship's going.
+const RAPID_FIRE_BULLETS: usize = 2; // How many times more bullets can be out with rapid fire.
+const RAPID_FIRE_SPEED: f32 = 1.5; // And how much quicker they go.
+const OWNER_IMMUNITY: f32 = 0.25; // Seconds before a ship's own bullets can hit it.
+const CLEARED_SPREAD: f32 = 0.5; // Things cleared off the field just crumble where they are.
+const ROCK_HIT: components::HitEffect = components::HitEffect(
+    Some(Sound::AsteroidExplode),
+    Color {
+        r: 0.7,
+        g: 0.7,
+        b: 0.7,
+        a: 1.0,
+    },
+    16,
+    30.0,
+);
+const BULLET_HIT: components::HitEffect = components::HitEffect(
+    None,
+    Color {
+        r: 0.7,
+        g: 0.0,
+        b: 0.0,
+        a: 1.0,
+    },
+    8,
+    10.0,
+);
+const HYPERSPACE_DURATION: f32 = 0.5; // Seconds spent out of the playfield.
+const HYPERSPACE_COOLDOWN: f32 = 2.0; // Seconds after coming back before it can be used again.
+pub const DRAG_REFERENCE_RATE: f32 = 120.0; // Ticks a second drag was tuned at, ship and particles both.
+const HYPERSPACE_FAILURE_CHANCE: f32 = 0.125; // The odds of not surviving the trip.
+
+// What the player is pressing for a single step. Analog values come from gamepads, keys are all or nothing.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Inputs {
+    pub thrust: f32,      // 0 to 1.
+    pub rotate: f32,      // -1 for all the way left, 1 for all the way right.
+    pub brake: f32,       // 0 to 1.
+    pub fire: bool,       // Only true on the step it was pressed.
+    pub hyperspace: bool, // Same as fire.
+    pub shield: bool,     // Held, so true for as long as it's down.
+}
+
+impl Inputs {
+    // Snapping the analog bits to what a replay can store, so a run plays back exactly.
+    pub fn quantized(&self) -> Self {
+        Self {
+            thrust: (self.thrust.clamp(0.0, 1.0) * 255.0).round() / 255.0,
+            rotate: (self.rotate.clamp(-1.0, 1.0) * 127.0).round() / 127.0,
+            brake: (self.brake.clamp(0.0, 1.0) * 255.0).round() / 255.0,
+            fire: self.fire,
+            hyperspace: self.hyperspace,
+            shield: self.shield,
+        }
+    }
+}
+
+// Sounds the simulation wants played. The binary drains these, since we can't touch audio in here.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Sound {
+    PlayerSpawn,
+    PlayerShoot,
+    PlayerDeathRock,
+    PlayerDeathLaser,
+    AsteroidExplode,
+    Hyperspace,
+    SaucerShoot,
+    SaucerExplode,
+    ExtraLife,
+    PowerUp,
+    ShieldHit,
+}
+
+// Somewhere on the edge, in a random direction from the middle.
+pub fn edge_point(rng: &mut Rng, playfield: &Playfield) -> (f32, f32) {
+    let angle = rng.gen_range(0.0, std::f32::consts::TAU);
+    let (center, direction) = (playfield.center(), (angle.cos(), angle.sin()));
+    // Whichever edge that direction hits first.
+    let reach = (playfield.width() / 2.0 / direction.0.abs())
+        .min(playfield.height() / 2.0 / direction.1.abs());
+    playfield.wrap((
+        center.0 + direction.0 * reach,
+        center.1 + direction.1 * reach,
+    ))
+}
+
+// Speed is the fastest it can drift along each axis.
+pub fn create_asteroid_point(
+    world: &mut World,
+    rng: &mut Rng,
+    point: (f32, f32),
+    size: f32,
+    speed: f32,
+) -> Entity {
+    let mut rock_shape = (0..16)
+        .map(|x| rotate_point((0.0, (rng.rand() % 2) as f32 + size), x as f32 * 22.5))
+        .collect::<Vec<(f32, f32)>>();
+    rock_shape.push(rock_shape[0]);
+    world.spawn((
+        components::Position(point.0, point.1, 0.0),
+        components::Velocity(
+            ((rng.rand() % 20) as f32 - 10.0) / 10.0 * speed,
+            ((rng.rand() % 20) as f32 - 10.0) / 10.0 * speed,
+            (rng.rand() % 20) as f32 - 10.0,
+        ),
+        components::Draw(
+            Color {
+                r: 0.7,
+                g: 0.7,
+                b: 0.7,
+                a: 1.0,
+            },
+            rock_shape,
+        ),
+        components::Collidable(size, components::CollidableType::ASTEROID),
+        components::CollisionLayers::default_for(components::CollidableType::ASTEROID),
+        components::Damages::default_for(components::CollidableType::ASTEROID),
+        ROCK_HIT,
+        components::PreciseCollision(),
+    ))
+}
+
+// Anyone's bullets, fired by owner. Lifetime is the seconds and distance it gets before fizzling.
+pub fn create_bullet(
+    world: &mut World,
+    owner: Entity,
+    position: (f32, f32, f32),
+    velocity: (f32, f32),
+    color: Color,
+    lifetime: (f32, f32),
+) -> Entity {
+    world.spawn((
+        components::Position(position.0, position.1, position.2),
+        components::Velocity(velocity.0, velocity.1, 0.0),
+        components::Draw(color, vec![(0.0, -1.0), (0.0, 1.0)]),
+        components::Collidable(1.0, components::CollidableType::BULLET),
+        components::Lifetime(lifetime.0, lifetime.1),
+        components::CollisionLayers::default_for(components::CollidableType::BULLET),
+        components::Damages::default_for(components::CollidableType::BULLET),
+        BULLET_HIT,
+        components::Owner(owner, OWNER_IMMUNITY),
+        components::FastMover(),
+    ))
+}
+
+// This, too, is yuri.
+pub fn world_reset(world: &mut World, playfield: &Playfield) {
+    world.clear(); // Resetting the world.
+                   // Getting our new things in, the player drifting in from the bottom edge.
+    let spawn_point = playfield.wrap((playfield.center().0, playfield.max.1));
+    world.spawn((
+        components::Position(spawn_point.0, spawn_point.1, 0.0),
+        components::Velocity(0.0, 20.0, 1125.0),
+        components::Draw(
+            Color {
+                r: 1.0,
+                g: 1.0,
+                b: 1.0,
+                a: 1.0,
+            },
+            vec![
+                (-2.0, 3.0),
+                (0.0, -3.0),
+                (2.0, 3.0),
+                (0.0, 1.0),
+                (-2.0, 3.0),
+            ],
+        ),
+        components::Collidable(1.0, components::CollidableType::PLAYER),
+        components::CollisionLayers::default_for(components::CollidableType::PLAYER),
+        components::Damages::default_for(components::CollidableType::PLAYER),
+        components::PreciseCollision(),
+        components::Controllable(),
+        components::Shield(1.0, false),
+    ));
+}
+
+// The whole simulation, minus anything that needs a window or speakers.
+pub struct Game {
+    // For Hecs
+    pub world: World,
+    pub particles: ParticleStorage,
+    pub popups: Vec<ScorePopup>, // Scores floating up off whatever was worth them.
+    pub broadphase: SpatialHash, // Kept around so the cells don't get reallocated every tick.
+    pub playfield: Playfield,    // Where everything lives, and wraps around.
+
+    // What happened this tick, and what to do about it once everyone's had a look.
+    pub collision_events: Vec<CollisionEvent>,
+    pub entities_to_destroy: Vec<Entity>,
+    pub asteroids_to_create: Vec<(f32, f32, f32)>,
+
+    // For tracking vars and the fun sorta things.
+    pub hitstun: f32,    // Yeah, the funny little hitstun thing returns, in seconds.
+    pub high_score: u32, // And a high score?
+    pub score: u32,      // Scooore!
+    pub lives: u32,      // ...And a new one, lives.
+    pub extra_life_flash: f32, // Seconds left of the HUD making a fuss about a life being handed out.
+    pub combo: Combo,          // The chain of hits going right now.
+    pub stats: RunStats,       // Tallies for the run so far.
+
+    // Item management.
+    pub clear_screen: bool,
+    pub bomb: bool, // The field's being cleared by a bomb rather than dying, so the wave still counts.
+    pub wave: u32,  // Which wave we're on, 0 before the first.
+    pub wave_delay: f32, // Seconds until the next wave comes in, once the field's clear.
+    pub saucer_cooldown: f32, // Seconds until the next saucer, once the last one's gone.
+    pub bullet_lifetime: f32, // How long a bullet lasts, in seconds.
+    pub bullet_range: f32, // And how far it can go.
+    pub max_bullets: usize, // How many bullets each ship can have out at once.
+    pub hyperspace_cooldown: f32, // Seconds until hyperspace can be used again.
+
+    pub difficulty: Difficulty, // How the run ramps up. Changes take effect on the next restart.
+    pub seed: u64,              // What this run was seeded with.
+    pub rng: Rng,               // Gameplay randomness only, particles and audio get their own.
+    pub precise_collisions: bool, // Checking outlines rather than just radii, for things that ask for it.
+    pub time: f64,                // How long we've been simulating for.
+    pub sounds: Vec<Sound>,       // Sounds queued up since the last drain.
+}
+
+impl Default for Game {
+    fn default() -> Self {
+        Self::new(0)
+    }
+}
+
+impl Game {
+    pub fn new(seed: u64) -> Self {
+        Self::with_playfield(seed, Playfield::default())
+    }
+
+    // For when the playfield isn't just what the camera sees.
+    pub fn with_playfield(seed: u64, playfield: Playfield) -> Self {
+        Self {
+            world: World::new(),
+            particles: ParticleStorage::new(Rng::stream(seed, rng::STREAM_COSMETIC)),
+            popups: Vec::new(),
+            broadphase: SpatialHash::new(BROADPHASE_CELL_SIZE, playfield.min, playfield.max),
+            playfield,
+            collision_events: Vec::new(),
+            entities_to_destroy: Vec::new(),
+            asteroids_to_create: Vec::new(),
+            hitstun: 0.0,
+            high_score: 0,
+            score: 0,
+            lives: 0,
+            extra_life_flash: 0.0,
+            combo: Combo::default(),
+            stats: RunStats::default(),
+            clear_screen: false,
+            bomb: false,
+            wave: 0,
+            wave_delay: wave::WAVE_DELAY,
+            saucer_cooldown: saucer::FIRST_SAUCER_DELAY,
+            bullet_lifetime: DEFAULT_BULLET_LIFETIME,
+            bullet_range: DEFAULT_BULLET_RANGE,
+            max_bullets: DEFAULT_MAX_BULLETS,
+            hyperspace_cooldown: 0.0,
+            difficulty: Difficulty::default(),
+            seed,
+            rng: Rng::stream(seed, rng::STREAM_GAMEPLAY),
+            precise_collisions: true,
+            time: 0.0,
+            sounds: Vec::new(),
+        }
+    }
+
+    // Starting a fresh run. The same seed and the same inputs always play out the same.
+    pub fn restart(&mut self, seed: u64) {
+        self.world = World::new(); // A fresh world, so leftover archetypes can't change iteration order.
+        world_reset(&mut self.world, &self.playfield);
+        self.broadphase =
+            SpatialHash::new(BROADPHASE_CELL_SIZE, self.playfield.min, self.playfield.max);
+        self.seed = seed;
+        self.rng = Rng::stream(seed, rng::STREAM_GAMEPLAY);
+        self.particles = ParticleStorage::new(Rng::stream(seed, rng::STREAM_COSMETIC));
+        self.popups.clear();
+        self.time = 0.0;
+        self.hitstun = 0.0;
+        self.clear_screen = false;
+        self.bomb = false;
+        self.wave = 0;
+        self.wave_delay = wave::WAVE_DELAY;
+        self.saucer_cooldown = saucer::FIRST_SAUCER_DELAY;
+        self.hyperspace_cooldown = 0.0;
+        self.high_score = self.high_score.max(self.score);
+        self.score = 0;
+        self.lives = 3;
+        self.extra_life_flash = 0.0;
+        self.combo = Combo::default();
+        self.stats = RunStats::default();
+        self.sounds.push(Sound::PlayerSpawn);
+    }
+
+    // Handing over every sound queued since the last call.
+    pub fn drain_sounds(&mut self) -> std::vec::Drain<'_, Sound> {
+        self.sounds.drain(..)
+    }
+
+    // Advancing the simulation by a single tick of delta_time seconds.
+    pub fn step(&mut self, inputs: &Inputs, delta_time: f32) {
+        self.time += delta_time as f64;
+
+        // Remembering where everything was, so the draw system can interpolate.
+        let mut missing_last_positions = Vec::new();
+        for (id, (position, last_position)) in self
+            .world
+            .query_mut::<(&components::Position, Option<&mut components::LastPosition>)>()
+        {
+            let current = components::LastPosition(position.0, position.1, position.2);
+            match last_position {
+                Some(last_position) => *last_position = current,
+                None => missing_last_positions.push((id, current)),
+            }
+        }
+        for (id, last_position) in missing_last_positions {
+            let _ = self.world.insert_one(id, last_position);
+        }
+
+        // Woo, physics.
+        // That means mutable bs, but at least we're prepared this time.
+        if self.hitstun <= 0.0 {
+            self.hitstun = 0.0;
+
+            // The best time for some periodic functions
+            self.wave_system(delta_time);
+
+            self.physics_pass(inputs, delta_time);
+        } else {
+            self.hitstun -= delta_time;
+        }
+
+        self.extra_life_flash = (self.extra_life_flash - delta_time).max(0.0);
+        self.particles.update(delta_time, &self.playfield);
+        self.popup_system(delta_time);
+    }
+
+    fn physics_pass(&mut self, inputs: &Inputs, delta_time: f32) {
+        // Prepping a destruction system.
+        let mut bullets_to_create: Vec<(Entity, f32, f32, f32, f32, f32, f32)> = Vec::new();
+
+        // Update velocities.
+        // With time slowed, anything that isn't the player's goes at a fraction of the speed.
+        let playfield = self.playfield;
+        let time_scale = self.time_scale();
+        let players = self
+            .world
+            .query_mut::<&components::Controllable>()
+            .into_iter()
+            .map(|(id, _)| id)
+            .collect::<Vec<_>>();
+        // How long this tick is for something, going by whether it's a player's or one of their bullets.
+        let scaled_delta = |id: Entity, owner: Option<&components::Owner>| {
+            if players.contains(&id) || owner.is_some_and(|owner| players.contains(&owner.0)) {
+                delta_time
+            } else {
+                delta_time * time_scale
+            }
+        };
+        for (id, (position, velocity, owner)) in self.world.query_mut::<(
+            &mut components::Position,
+            &components::Velocity,
+            Option<&components::Owner>,
+        )>() {
+            let delta_time = scaled_delta(id, owner);
+            // Moving, and wrapping around the playfield if that took us off it.
+            (position.0, position.1) = playfield.wrap((
+                position.0 + velocity.0 * delta_time,
+                position.1 + velocity.1 * delta_time,
+            ));
+            position.2 = (position.2 + velocity.2 * delta_time) % 360.0; // Same for degrees.
+        }
+        self.lifetime_system(scaled_delta);
+        self.combo_system(delta_time);
+        self.hyperspace_system(delta_time);
+        self.respawn_system(delta_time);
+        self.power_up_system(delta_time);
+        self.shield_system(inputs, delta_time);
+        self.magnet_system(delta_time * time_scale);
+        self.saucer_system(delta_time * time_scale);
+
+        // Then get a collection of all possible, collidable objects, with outlines for the precise ones.
+        let precise_collisions = self.precise_collisions;
+        let collidable_objects = self
+            .world
+            .query::<(
+                &components::Position,
+                &components::Collidable,
+                Option<&components::Draw>,
+                Option<&components::PreciseCollision>,
+                Option<(&components::Velocity, &components::FastMover)>,
+                Option<&components::CollisionLayers>,
+                Option<&components::Owner>,
+                Option<&components::Shield>,
+            )>()
+            .without::<&components::Hyperspace>()
+            .without::<&components::Respawning>()
+            .without::<&components::Invulnerable>()
+            .iter()
+            .map(
+                |(e, (&p, &c, draw, precise, fast, layers, owner, shield))| {
+                    // With the shield up, the bubble's what gets hit, not the hull.
+                    let shielded = shield.is_some_and(|shield| shield.1);
+                    let c = if shielded {
+                        components::Collidable(shield::SHIELD_RADIUS, c.1)
+                    } else {
+                        c
+                    };
+                    let outline = match (draw, precise) {
+                        (Some(draw), Some(_)) if precise_collisions && !shielded => {
+                            Some(narrowphase::transform_outline(&draw.1, &p))
+                        }
+                        _ => None,
+                    };
+                    let reach = match (draw, &outline) {
+                        (Some(draw), Some(_)) => narrowphase::bounding_radius(&draw.1),
+                        _ => c.0,
+                    };
+                    // How far fast movers went this tick, so we can check the whole way.
+                    let delta_time = scaled_delta(e, owner);
+                    let sweep = fast
+                        .map(|(velocity, _)| (velocity.0 * delta_time, velocity.1 * delta_time));
+                    let layers = layers
+                        .copied()
+                        .unwrap_or_else(|| components::CollisionLayers::default_for(c.1));
+                    (e, p, c, outline, reach, sweep, layers, owner.copied())
+                },
+            )
+            .collect::<Vec<_>>();
+
+        // Letting the grid narrow it down to things that are at least nearby.
+        self.broadphase.clear();
+        for (index, collidable) in collidable_objects.iter().enumerate() {
+            // Fast movers go in as a circle around their whole path.
+            let sweep = collidable.5.unwrap_or((0.0, 0.0));
+            self.broadphase.insert(
+                index,
+                (
+                    collidable.1 .0 - sweep.0 / 2.0,
+                    collidable.1 .1 - sweep.1 / 2.0,
+                ),
+                collidable.4 + (sweep.0 * sweep.0 + sweep.1 * sweep.1).sqrt() / 2.0,
+            );
+        }
+
+        // Now performing collision checks, once per pair, and writing down what hit what.
+        self.collision_events.clear();
+        for (first, second) in self.broadphase.candidate_pairs() {
+            let (this, other) = (&collidable_objects[first], &collidable_objects[second]);
+            // Skipping anything that doesn't want to hear about the other, and ships touching their own fresh bullets.
+            if !this.6.accepts(&other.6)
+                || this.7.is_some_and(|owner| owner.ignores(other.0))
+                || other.7.is_some_and(|owner| owner.ignores(this.0))
+            {
+                continue;
+            }
+            // Wherever the other one is closest to us, which might be across the seam.
+            let delta = playfield.delta((this.1 .0, this.1 .1), (other.1 .0, other.1 .1));
+            let near = (this.1 .0 + delta.0, this.1 .1 + delta.1);
+            let shift = (near.0 - other.1 .0, near.1 - other.1 .1);
+            // Looking at it as if the other one stood still, and we did all the moving this tick.
+            // Things that aren't fast movers just haven't moved, so this is the usual distance for them.
+            let sweep = match (this.5, other.5) {
+                (None, None) => (0.0, 0.0),
+                (this_sweep, other_sweep) => {
+                    let (this_sweep, other_sweep) = (
+                        this_sweep.unwrap_or((0.0, 0.0)),
+                        other_sweep.unwrap_or((0.0, 0.0)),
+                    );
+                    (this_sweep.0 - other_sweep.0, this_sweep.1 - other_sweep.1)
+                }
+            };
+            let this_path = (
+                (this.1 .0 - sweep.0, this.1 .1 - sweep.1),
+                (this.1 .0, this.1 .1),
+            );
+            let other_path = ((near.0 + sweep.0, near.1 + sweep.1), near);
+            let distance_squared =
+                narrowphase::segment_distance_squared(near, this_path.0, this_path.1);
+            let touching = match (&this.3, &other.3) {
+                // Neither cares about shape, so it's just the radius check.
+                (None, None) => distance_squared < this.2 .0.powf(2.0) + other.2 .0.powf(2.0),
+                // Otherwise the radius check just tells us whether it's worth looking closer.
+                (this_outline, other_outline) => {
+                    distance_squared < (this.4 + other.4).powf(2.0)
+                        && match (this_outline, other_outline) {
+                            (Some(this_outline), Some(other_outline)) => {
+                                narrowphase::outlines_touch(
+                                    this_outline,
+                                    &narrowphase::translate_outline(other_outline, shift),
+                                )
+                            }
+                            (Some(outline), None) => narrowphase::swept_circle_touches_outline(
+                                other_path, other.2 .0, outline,
+                            ),
+                            (None, Some(outline)) => narrowphase::swept_circle_touches_outline(
+                                this_path,
+                                this.2 .0,
+                                &narrowphase::translate_outline(outline, shift),
+                            ),
+                            (None, None) => false,
+                        }
+                }
+            };
+            if touching {
+                let weight = this.2 .0 / (this.2 .0 + other.2 .0);
+                self.collision_events.push(CollisionEvent {
+                    a: this.0,
+                    b: other.0,
+                    kind_a: this.2 .1,
+                    kind_b: other.2 .1,
+                    point: playfield
+                        .wrap((this.1 .0 + delta.0 * weight, this.1 .1 + delta.1 * weight)),
+                });
+            }
+        }
+
+        // And letting everything that cares react to it.
+        self.damage_system();
+        self.scoring_system();
+        self.audio_system();
+        self.vfx_system();
+        self.splitting_system();
+        self.explosion_system();
+        self.bounce_system();
+        self.drop_system();
+        self.pickup_system();
+
+        if self.clear_screen {
+            let mut cleared_rocks = false;
+            for collidable in collidable_objects {
+                // Anything that could hurt the player goes, which leaves them and their pickups.
+                if self.damages_of(collidable.0) & components::LAYER_PLAYER == 0 {
+                    continue;
+                }
+                cleared_rocks |= collidable.6 .0 & components::LAYER_ASTEROID != 0;
+                let effect = self
+                    .world
+                    .get::<&components::HitEffect>(collidable.0)
+                    .map(|effect| *effect);
+                if let Ok(effect) = effect {
+                    self.debris((collidable.1 .0, collidable.1 .1), effect, CLEARED_SPREAD);
+                }
+                self.entities_to_destroy.push(collidable.0);
+            }
+            self.clear_screen = false;
+            // Dying clears the field, which shouldn't count as beating the wave, so it starts over.
+            if cleared_rocks && !self.bomb {
+                self.wave = self.wave.saturating_sub(1);
+            }
+            self.bomb = false;
+        }
+
+        // Counting what each ship still has out there, so nobody goes over their limit.
+        let mut live_bullets: Vec<(Entity, usize)> = Vec::new();
+        for (id, owner) in self.world.query_mut::<&components::Owner>() {
+            if self.entities_to_destroy.contains(&id) {
+                continue;
+            }
+            match live_bullets.iter_mut().find(|(ship, _)| *ship == owner.0) {
+                Some((_, count)) => *count += 1,
+                None => live_bullets.push((owner.0, 1)),
+            }
+        }
+
+        // Updating player controls, for anyone who isn't off in hyperspace.
+        let mut jumps = Vec::new();
+        for (id, (position, velocity, power_ups, _controls)) in self
+            .world
+            .query_mut::<(
+                &components::Position,
+                &mut components::Velocity,
+                Option<&components::PowerUps>,
+                &components::Controllable,
+            )>()
+            .without::<&components::Hyperspace>()
+            .without::<&components::Respawning>()
+        {
+            let new_velocity = rotate_point((0.0, -6.0 * delta_time), position.2);
+            velocity.0 *= 1.0 - inputs.brake * delta_time;
+            velocity.1 *= 1.0 - inputs.brake * delta_time;
+            velocity.2 *= 1.0 - inputs.brake * delta_time;
+
+            // Drag, as it was per tick at the tick rate it was tuned for, so other rates feel the same.
+            let drag = 0.995_f32.powf(delta_time * DRAG_REFERENCE_RATE);
+            let spin_drag = 0.975_f32.powf(delta_time * DRAG_REFERENCE_RATE);
+            velocity.0 = (velocity.0 * drag) + (new_velocity.0 * inputs.thrust);
+            velocity.1 = (velocity.1 * drag) + (new_velocity.1 * inputs.thrust);
+            velocity.2 = (velocity.2 * spin_drag) + 135.0 * delta_time * inputs.rotate;
+
+            let bullets_out = live_bullets
+                .iter()
+                .find(|(ship, _)| *ship == id)
+                .map_or(0, |(_, count)| *count);
+            let has = |kind| power_ups.is_some_and(|power_ups| power_ups.has(kind));
+            let (max_bullets, bullet_speed) = if has(PowerUpKind::RapidFire) {
+                (
+                    self.max_bullets * RAPID_FIRE_BULLETS,
+                    BULLET_SPEED * RAPID_FIRE_SPEED,
+                )
+            } else {
+                (self.max_bullets, BULLET_SPEED)
+            };
+            if inputs.fire && bullets_out < max_bullets {
+                // Middle one first, so a spread that only has room for one still goes straight.
+                let spread: &[f32] = if has(PowerUpKind::SpreadShot) {
+                    &[0.0, -SPREAD_ANGLE, SPREAD_ANGLE]
+                } else {
+                    &[0.0]
+                };
+                let spread = &spread[..spread.len().min(max_bullets - bullets_out)];
+                for angle in spread {
+                    bullets_to_create.push((
+                        id,
+                        position.0,
+                        position.1,
+                        position.2 + angle,
+                        velocity.0,
+                        velocity.1,
+                        bullet_speed,
+                    ));
+                }
+                self.sounds.push(Sound::PlayerShoot);
+                self.stats.shots_fired += spread.len() as u32; // Every bullet's a shot, so accuracy stays out of 100.
+            }
+            if inputs.hyperspace && self.hyperspace_cooldown <= 0.0 {
+                jumps.push((id, *position));
+            }
+            if inputs.thrust > 0.0 {
+                let backwards = rotate_point((0.0, 4.0), position.2);
+                self.particles.create_particle(
+                    1,
+                    (position.0 + backwards.0, position.1 + backwards.1),
+                    (backwards.0, backwards.1),
+                    1.0,
+                    1.0,
+                    Color {
+                        r: 0.6,
+                        g: 0.6,
+                        b: 0.6,
+                        a: 1.0,
+                    },
+                    1.0,
+                    (0.0, 0.0),
+                    (0.0, 0.0),
+                    0.0,
+                    0.2,
+                );
+            }
+            if inputs.rotate < 0.0 {
+                let backwards = rotate_point((2.0, -2.0), position.2);
+                self.particles.create_particle(
+                    1,
+                    (position.0 + backwards.0, position.1 + backwards.1),
+                    (backwards.0, backwards.1),
+                    1.0,
+                    1.0,
+                    Color {
+                        r: 0.5,
+                        g: 0.5,
+                        b: 0.5,
+                        a: 1.0,
+                    },
+                    0.7,
+                    (0.0, 0.0),
+                    (0.0, 0.0),
+                    0.0,
+                    0.2,
+                );
+            }
+            if inputs.rotate > 0.0 {
+                let backwards = rotate_point((-2.0, -2.0), position.2);
+                self.particles.create_particle(
+                    1,
+                    (position.0 + backwards.0, position.1 + backwards.1),
+                    (backwards.0, backwards.1),
+                    1.0,
+                    1.0,
+                    Color {
+                        r: 0.5,
+                        g: 0.5,
+                        b: 0.5,
+                        a: 1.0,
+                    },
+                    0.7,
+                    (0.0, 0.0),
+                    (0.0, 0.0),
+                    0.0,
+                    0.2,
+                );
+            }
+        }
+
+        // Vanishing anyone who jumped, to somewhere random.
+        for (id, position) in jumps {
+            let destination = (
+                self.rng
+                    .gen_range(self.playfield.min.0, self.playfield.max.0),
+                self.rng
+                    .gen_range(self.playfield.min.1, self.playfield.max.1),
+            );
+            let _ = self
+                .world
+                .insert_one(id, components::Hyperspace(HYPERSPACE_DURATION, destination));
+            self.hyperspace_cooldown = HYPERSPACE_DURATION + HYPERSPACE_COOLDOWN;
+            self.hyperspace_particles((position.0, position.1));
+            self.sounds.push(Sound::Hyperspace);
+        }
+
+        // Destroying all things meant to be destroyed.
+        self.entities_to_destroy.dedup();
+        for entity in self.entities_to_destroy.drain(..) {
+            // Finally getting rid of it.
+            let _ = self.world.despawn(entity);
+        }
+
+        // And making new things.
+        self.asteroids_to_create.dedup();
+        let asteroid_speed = self.setting(self.difficulty.asteroid_speed);
+        for asteroid in std::mem::take(&mut self.asteroids_to_create) {
+            create_asteroid_point(
+                &mut self.world,
+                &mut self.rng,
+                (asteroid.0, asteroid.1),
+                asteroid.2,
+                asteroid_speed,
+            );
+        }
+
+        bullets_to_create.dedup();
+        for bullet in bullets_to_create {
+            let bullet_position = rotate_point((0.0, -4.5), bullet.3); // Clear of the nose, so we don't shoot ourselves.
+            let bulet_velocity = rotate_point((0.0, -bullet.6), bullet.3);
+            let spawn_point = self
+                .playfield
+                .wrap((bullet.1 + bullet_position.0, bullet.2 + bullet_position.1));
+            create_bullet(
+                &mut self.world,
+                bullet.0,
+                (spawn_point.0, spawn_point.1, bullet.3),
+                (bullet.4 + bulet_velocity.0, bullet.5 + bulet_velocity.1),
+                Color {
+                    r: 1.0,
+                    g: 0.2,
+                    b: 0.2,
+                    a: 1.0,
+                },
+                (self.bullet_lifetime, self.bullet_range),
+            );
+        }
+    }
+
+    // Running down everything that doesn't last forever, and fizzling out whatever's done.
+    // Going by the same slowed-or-not tick that moved each thing, so a slowed bullet still gets its full range.
+    fn lifetime_system(
+        &mut self,
+        scaled_delta: impl Fn(Entity, Option<&components::Owner>) -> f32,
+    ) {
+        let mut expired = Vec::new();
+        for (id, (position, velocity, lifetime, owner)) in self.world.query_mut::<(
+            &components::Position,
+            &components::Velocity,
+            &mut components::Lifetime,
+            Option<&components::Owner>,
+        )>() {
+            let delta_time = scaled_delta(id, owner);
+            lifetime.0 -= delta_time;
+            lifetime.1 -= (velocity.0 * velocity.0 + velocity.1 * velocity.1).sqrt() * delta_time;
+            if lifetime.0 <= 0.0 || lifetime.1 <= 0.0 {
+                self.particles.create_particle(
+                    4,
+                    (position.0, position.1),
+                    (velocity.0 * 0.25, velocity.1 * 0.25),
+                    0.9,
+                    0.4,
+                    Color {
+                        r: 0.5,
+                        g: 0.1,
+                        b: 0.1,
+                        a: 1.0,
+                    },
+                    0.3,
+                    (0.0, 0.0),
+                    (4.0, 4.0),
+                    0.1,
+                    0.1,
+                );
+                expired.push(id);
+            }
+        }
+        // Anything the player fired running out is a miss, and that's the chain gone.
+        for id in expired {
+            if self.fired_by_player(id) {
+                self.combo_miss();
+            }
+            self.entities_to_destroy.push(id);
+        }
+        for (id, owner) in self.world.query_mut::<&mut components::Owner>() {
+            owner.1 -= scaled_delta(id, Some(owner));
+        }
+    }
+
+    // Bringing anyone in hyperspace back once their time's up, if they make it.
+    fn hyperspace_system(&mut self, delta_time: f32) {
+        self.hyperspace_cooldown -= delta_time;
+        let mut arrivals = Vec::new();
+        for (id, hyperspace) in self.world.query_mut::<&mut components::Hyperspace>() {
+            hyperspace.0 -= delta_time;
+            if hyperspace.0 <= 0.0 {
+                arrivals.push((id, hyperspace.1));
+            }
+        }
+        for (id, destination) in arrivals {
+            let _ = self.world.remove_one::<components::Hyperspace>(id);
+            if let Ok((position, velocity)) = self
+                .world
+                .query_one_mut::<(&mut components::Position, &mut components::Velocity)>(id)
+            {
+                (position.0, position.1) = destination;
+                (velocity.0, velocity.1) = (0.0, 0.0);
+            }
+            self.hyperspace_particles(destination);
+            if self.rng.gen_range(0.0, 1.0) < HYPERSPACE_FAILURE_CHANCE {
+                // Didn't quite make it back in one piece.
+                self.particles.create_particle(
+                    16,
+                    destination,
+                    (0.0, 0.0),
+                    0.95,
+                    1.0,
+                    Color {
+                        r: 0.9,
+                        g: 0.9,
+                        b: 0.9,
+                        a: 1.0,
+                    },
+                    2.0,
+                    (0.0, 0.0),
+                    (50.0, 50.0),
+                    0.5,
+                    0.2,
+                );
+                self.sounds.push(Sound::PlayerDeathRock);
+                self.lose_life(id);
+            }
+        }
+    }
+
+    // The shimmer left behind going into hyperspace, and coming back out.
+    fn hyperspace_particles(&mut self, point: (f32, f32)) {
+        self.particles.create_particle(
+            12,
+            point,
+            (0.0, 0.0),
+            0.9,
+            0.6,
+            Color {
+                r: 0.5,
+                g: 0.7,
+                b: 1.0,
+                a: 1.0,
+            },
+            0.4,
+            (3.0, 3.0),
+            (6.0, 6.0),
+            0.2,
+            0.1,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Half a minute of someone spinning, thrusting and firing on a fixed pattern, and where it left everything.
+    fn play(seed: u64) -> (u32, u32, u32, Vec<(f32, f32, f32)>) {
+        let mut game = Game::new(seed);
+        game.restart(seed);
+        for tick in 0..(30 * 120) {
+            let inputs = Inputs {
+                thrust: (tick / 240 % 2) as f32,
+                rotate: if tick % 500 < 200 { 1.0 } else { -0.5 },
+                fire: tick % 30 == 0,
+                hyperspace: tick % 1800 == 900,
+                ..Default::default()
+            };
+            game.step(&inputs, 1.0 / 120.0);
+        }
+        let world = game
+            .world
+            .query::<&components::Position>()
+            .iter()
+            .map(|(_id, position)| (position.0, position.1, position.2))
+            .collect();
+        (game.score, game.lives, game.wave, world)
+    }
+
+    #[test]
+    fn same_seed_same_run() {
+        let run = play(42);
+        assert!(run.2 > 0 && run.3.len() > 1);
+        assert_eq!(run, play(42));
+    }
+
+    // A fresh run with the first wave and saucer held off, so bullets have nothing to hit, and the
+    // ship sat still in the middle rather than drifting in. Pointing across the long way, so its own
+    // bullets don't come back round into it.
+    fn empty_field(seed: u64) -> Game {
+        let mut game = Game::new(seed);
+        game.restart(seed);
+        game.wave_delay = 1000.0;
+        game.saucer_cooldown = 1000.0;
+        let center = game.playfield.center();
+        for (_id, (position, velocity, _)) in game.world.query_mut::<(
+            &mut components::Position,
+            &mut components::Velocity,
+            &components::Controllable,
+        )>() {
+            *position = components::Position(center.0, center.1, 90.0);
+            *velocity = components::Velocity(0.0, 0.0, 0.0);
+        }
+        game
+    }
+
+    fn player(game: &mut Game) -> Entity {
+        game.world
+            .query_mut::<&components::Controllable>()
+            .into_iter()
+            .next()
+            .unwrap()
+            .0
+    }
+
+    fn bullets_out(game: &mut Game) -> usize {
+        game.world
+            .query_mut::<&components::Owner>()
+            .into_iter()
+            .count()
+    }
+
+    // Firing once, then coasting for however long, and whether the bullet's still about.
+    fn bullet_alive_after(game: &mut Game, seconds: f32) -> bool {
+        let fire = Inputs {
+            fire: true,
+            ..Default::default()
+        };
+        game.step(&fire, 1.0 / 120.0);
+        assert_eq!(bullets_out(game), 1);
+        for _ in 0..(seconds * 120.0).round() as u32 {
+            game.step(&Inputs::default(), 1.0 / 120.0);
+        }
+        bullets_out(game) == 1
+    }
+
+    #[test]
+    fn bullets_run_out_of_time() {
+        let mut game = empty_field(1);
+        game.bullet_range = 1000.0;
+        assert!(bullet_alive_after(&mut game, DEFAULT_BULLET_LIFETIME - 0.1));
+        let mut game = empty_field(1);
+        game.bullet_range = 1000.0;
+        assert!(!bullet_alive_after(
+            &mut game,
+            DEFAULT_BULLET_LIFETIME + 0.1
+        ));
+    }
+
+    #[test]
+    fn bullets_run_out_of_range() {
+        // The ship's sat still, so a bullet covers BULLET_SPEED a second.
+        let mut game = empty_field(1);
+        game.bullet_lifetime = 1000.0;
+        assert!(bullet_alive_after(
+            &mut game,
+            0.9 * DEFAULT_BULLET_RANGE / BULLET_SPEED
+        ));
+        let mut game = empty_field(1);
+        game.bullet_lifetime = 1000.0;
+        assert!(!bullet_alive_after(
+            &mut game,
+            1.1 * DEFAULT_BULLET_RANGE / BULLET_SPEED
+        ));
+    }
+
+    #[test]
+    fn slowed_bullets_last_as_far_as_they_go() {
+        let mut game = empty_field(1);
+        let ship = player(&mut game);
+        game.world
+            .insert_one(
+                ship,
+                components::PowerUps(vec![(PowerUpKind::SlowTime, 100.0)]),
+            )
+            .unwrap();
+        // Somebody else's, off in the corner heading along the edge.
+        let saucer = game.world.spawn(());
+        let corner = (game.playfield.min.0 + 1.0, game.playfield.min.1 + 1.0);
+        create_bullet(
+            &mut game.world,
+            saucer,
+            (corner.0, corner.1, 90.0),
+            (10.0, 0.0),
+            WHITE,
+            (1.0, 1000.0),
+        );
+        // At half speed, its one second takes two.
+        for _ in 0..(1.5 * 120.0) as u32 {
+            game.step(&Inputs::default(), 1.0 / 120.0);
+        }
+        assert_eq!(bullets_out(&mut game), 1);
+        for _ in 0..(0.6 * 120.0) as u32 {
+            game.step(&Inputs::default(), 1.0 / 120.0);
+        }
+        assert_eq!(bullets_out(&mut game), 0);
+    }
+
+    #[test]
+    fn fifth_shot_is_refused() {
+        let mut game = empty_field(1);
+        let fire = Inputs {
+            fire: true,
+            ..Default::default()
+        };
+        for _ in 0..DEFAULT_MAX_BULLETS + 1 {
+            game.step(&fire, 1.0 / 120.0);
+        }
+        assert_eq!(bullets_out(&mut game), DEFAULT_MAX_BULLETS);
+        assert_eq!(game.stats.shots_fired, DEFAULT_MAX_BULLETS as u32);
+    }
+
+    #[test]
+    fn spread_shots_count_every_bullet() {
+        let mut game = Game::new(1);
+        game.restart(1);
+        let (ship, _) = game
+            .world
+            .query_mut::<&components::Controllable>()
+            .into_iter()
+            .next()
+            .unwrap();
+        game.world
+            .insert_one(
+                ship,
+                components::PowerUps(vec![(PowerUpKind::SpreadShot, 10.0)]),
+            )
+            .unwrap();
+        let fire = Inputs {
+            fire: true,
+            ..Default::default()
+        };
+        game.step(&fire, 1.0 / 120.0);
+        let bullets = game
+            .world
+            .query_mut::<&components::Owner>()
+            .into_iter()
+            .count();
+        assert_eq!(bullets, 3);
+        assert_eq!(game.stats.shots_fired, 3);
+
+        // Second volley only has room for one more under the cap.
+        for _ in 0..10 {
+            game.step(&fire, 1.0 / 120.0);
+            let bullets = game
+                .world
+                .query_mut::<&components::Owner>()
+                .into_iter()
+                .count();
+            assert!(bullets <= game.max_bullets);
+        }
+        assert_eq!(game.stats.shots_fired, game.max_bullets as u32);
+    }
+
+    #[test]
+    fn drag_is_the_same_at_any_tick_rate() {
+        let coast = |tick_rate: u32| {
+            let mut game = Game::new(1);
+            game.restart(1);
+            for _ in 0..tick_rate * 2 {
+                game.step(&Inputs::default(), 1.0 / tick_rate as f32);
+            }
+            let mut query = game
+                .world
+                .query::<(&components::Velocity, &components::Controllable)>();
+            let (_, (velocity, _)) = query.iter().next().unwrap();
+            (velocity.1, velocity.2)
+        };
+        let (slow, fast) = (coast(60), coast(240));
+        assert!((slow.0 - fast.0).abs() < 1e-2 && (slow.1 - fast.1).abs() < 1e-1);
+    }
+
+    fn run(game: &mut Game, seconds: f32, inputs: &Inputs) {
+        for _ in 0..(seconds * 120.0).round() as u32 {
+            game.step(inputs, 1.0 / 120.0);
+        }
+    }
+
+    // Jumping, and where to.
+    fn jump(game: &mut Game) -> (f32, f32) {
+        let hyperspace = Inputs {
+            hyperspace: true,
+            ..Default::default()
+        };
+        game.step(&hyperspace, 1.0 / 120.0);
+        let ship = player(game);
+        let destination = game.world.get::<&components::Hyperspace>(ship).unwrap().1;
+        destination
+    }
+
+    #[test]
+    fn hyperspace_lands_you_stopped_somewhere_else() {
+        // Some runs don't make it back, so trying seeds until we get both.
+        let (mut made_it, mut didnt) = (false, false);
+        for seed in 0..64 {
+            let mut game = empty_field(seed);
+            let ship = player(&mut game);
+            game.world.get::<&mut components::Velocity>(ship).unwrap().0 = 10.0;
+            let destination = jump(&mut game);
+
+            // Nothing can touch it while it's gone.
+            let here = game
+                .world
+                .get::<&components::Position>(ship)
+                .map(|p| (p.0, p.1))
+                .unwrap();
+            let rock = create_asteroid_point(&mut game.world, &mut game.rng, here, 5.0, 0.0);
+            run(&mut game, HYPERSPACE_DURATION / 2.0, &Inputs::default());
+            assert_eq!(game.lives, 3);
+            game.world.despawn(rock).unwrap();
+
+            run(
+                &mut game,
+                HYPERSPACE_DURATION / 2.0 + 0.05,
+                &Inputs::default(),
+            );
+            assert!(game.world.get::<&components::Hyperspace>(ship).is_err());
+            let velocity = *game.world.get::<&components::Velocity>(ship).unwrap();
+            assert_eq!((velocity.0, velocity.1), (0.0, 0.0));
+            if game.world.get::<&components::Respawning>(ship).is_ok() {
+                // Came back in bits, and that's the one life.
+                assert_eq!(game.lives, 2);
+                didnt = true;
+            } else {
+                let position = *game.world.get::<&components::Position>(ship).unwrap();
+                assert_eq!((position.0, position.1), destination);
+                assert_eq!(game.lives, 3);
+                made_it = true;
+            }
+        }
+        assert!(made_it && didnt);
+    }
+
+    #[test]
+    fn hyperspace_has_to_cool_down() {
+        let mut game = empty_field(1);
+        let ship = player(&mut game);
+        jump(&mut game);
+        run(&mut game, HYPERSPACE_DURATION + 0.05, &Inputs::default());
+        assert!(game.world.get::<&components::Respawning>(ship).is_err());
+
+        let hyperspace = Inputs {
+            hyperspace: true,
+            ..Default::default()
+        };
+        game.step(&hyperspace, 1.0 / 120.0);
+        assert!(game.world.get::<&components::Hyperspace>(ship).is_err());
+        run(&mut game, HYPERSPACE_COOLDOWN, &Inputs::default());
+        game.step(&hyperspace, 1.0 / 120.0);
+        assert!(game.world.get::<&components::Hyperspace>(ship).is_ok());
+    }
+}
@@ -0,0 +1,261 @@
+use std::fs::File;
+use std::io::Read;
+
+const AXIS_COUNT: usize = 8;
+const BUTTON_COUNT: usize = 16;
+const DEADZONE: f32 = 0.2; // Sticks never quite sit at zero.
+pub const ACTIVE_THRESHOLD: f32 = 0.5; // How far an analog control has to go to count as pressed.
+const RECONNECT_INTERVAL: u32 = 120; // How many polls between looking for a pad that isn't there.
+
+// The bits of a pad we know how to bind, laid out like an Xbox pad.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PadControl {
+    South,
+    East,
+    West,
+    North,
+    LeftBumper,
+    RightBumper,
+    Select,
+    Start,
+    LeftTrigger,
+    RightTrigger,
+    StickUp,
+    StickDown,
+    StickLeft,
+    StickRight,
+}
+
+impl PadControl {
+    pub const ALL: [PadControl; 14] = [
+        PadControl::South,
+        PadControl::East,
+        PadControl::West,
+        PadControl::North,
+        PadControl::LeftBumper,
+        PadControl::RightBumper,
+        PadControl::Select,
+        PadControl::Start,
+        PadControl::LeftTrigger,
+        PadControl::RightTrigger,
+        PadControl::StickUp,
+        PadControl::StickDown,
+        PadControl::StickLeft,
+        PadControl::StickRight,
+    ];
+
+    // What it's called in the config file.
+    pub fn name(&self) -> &'static str {
+        match self {
+            PadControl::South => "PadSouth",
+            PadControl::East => "PadEast",
+            PadControl::West => "PadWest",
+            PadControl::North => "PadNorth",
+            PadControl::LeftBumper => "PadLeftBumper",
+            PadControl::RightBumper => "PadRightBumper",
+            PadControl::Select => "PadSelect",
+            PadControl::Start => "PadStart",
+            PadControl::LeftTrigger => "PadLeftTrigger",
+            PadControl::RightTrigger => "PadRightTrigger",
+            PadControl::StickUp => "PadStickUp",
+            PadControl::StickDown => "PadStickDown",
+            PadControl::StickLeft => "PadStickLeft",
+            PadControl::StickRight => "PadStickRight",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<PadControl> {
+        PadControl::ALL
+            .into_iter()
+            .find(|control| control.name() == name)
+    }
+
+    // How far it's pushed, from 0 to 1, given a pad's raw axes and buttons.
+    fn value_of(&self, axes: &[f32; AXIS_COUNT], buttons: &[bool; BUTTON_COUNT]) -> f32 {
+        let button = |index: usize| buttons[index] as u32 as f32;
+        // Triggers rest at -1 and go to 1.
+        let trigger = |index: usize| ((axes[index] + 1.0) / 2.0).clamp(0.0, 1.0);
+        let stick = |value: f32| ((value - DEADZONE) / (1.0 - DEADZONE)).clamp(0.0, 1.0);
+        match self {
+            PadControl::South => button(0),
+            PadControl::East => button(1),
+            PadControl::West => button(2),
+            PadControl::North => button(3),
+            PadControl::LeftBumper => button(4),
+            PadControl::RightBumper => button(5),
+            PadControl::Select => button(6),
+            PadControl::Start => button(7),
+            PadControl::LeftTrigger => trigger(2),
+            PadControl::RightTrigger => trigger(5),
+            PadControl::StickUp => stick(-axes[1]),
+            PadControl::StickDown => stick(axes[1]),
+            PadControl::StickLeft => stick(-axes[0]),
+            PadControl::StickRight => stick(axes[0]),
+        }
+    }
+}
+
+// Where the axes sit with nobody touching them. Triggers rest all the way out, and we won't hear
+// about it until they move.
+fn neutral_axes() -> [f32; AXIS_COUNT] {
+    let mut axes = [0.0; AXIS_COUNT];
+    axes[2] = -1.0;
+    axes[5] = -1.0;
+    axes
+}
+
+// A single joystick, read straight off the Linux joystick interface so we don't need another crate.
+#[derive(Debug)]
+pub struct Gamepad {
+    file: Option<File>,
+    axes: [f32; AXIS_COUNT],
+    buttons: [bool; BUTTON_COUNT],
+    previous_axes: [f32; AXIS_COUNT], // What things looked like on the last poll, for presses.
+    previous_buttons: [bool; BUTTON_COUNT],
+    polls_since_connect: u32,
+}
+
+impl Default for Gamepad {
+    fn default() -> Self {
+        Self {
+            file: None,
+            axes: neutral_axes(),
+            buttons: [false; BUTTON_COUNT],
+            previous_axes: neutral_axes(),
+            previous_buttons: [false; BUTTON_COUNT],
+            polls_since_connect: 0,
+        }
+    }
+}
+
+impl Gamepad {
+    // Grabbing the first pad we can find, if any.
+    pub fn open() -> Self {
+        let mut gamepad = Self::default();
+        gamepad.connect();
+        gamepad
+    }
+
+    #[cfg(target_os = "linux")]
+    fn connect(&mut self) {
+        use std::os::unix::fs::OpenOptionsExt;
+        const O_NONBLOCK: i32 = 0o4000;
+
+        self.file = (0..4).find_map(|index| {
+            std::fs::OpenOptions::new()
+                .read(true)
+                .custom_flags(O_NONBLOCK)
+                .open(format!("/dev/input/js{}", index))
+                .ok()
+        });
+        self.axes = neutral_axes();
+        self.buttons = [false; BUTTON_COUNT];
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn connect(&mut self) {
+        self.file = None;
+        self.axes = neutral_axes();
+        self.buttons = [false; BUTTON_COUNT];
+    }
+
+    // Reading every event that's come in since last time. Call this once a frame.
+    pub fn poll(&mut self) {
+        self.previous_axes = self.axes;
+        self.previous_buttons = self.buttons;
+
+        if self.file.is_none() {
+            self.polls_since_connect += 1;
+            if self.polls_since_connect >= RECONNECT_INTERVAL {
+                self.polls_since_connect = 0;
+                self.connect();
+            }
+            return;
+        }
+
+        // Each event is a u32 timestamp, an i16 value, a u8 type and a u8 number.
+        let mut event = [0u8; 8];
+        loop {
+            let result = match &mut self.file {
+                Some(file) => file.read_exact(&mut event),
+                None => return,
+            };
+            match result {
+                Ok(()) => {
+                    let value = i16::from_le_bytes([event[4], event[5]]);
+                    let number = event[7] as usize;
+                    match event[6] & !0x80 {
+                        0x01 if number < BUTTON_COUNT => self.buttons[number] = value != 0,
+                        0x02 if number < AXIS_COUNT => {
+                            self.axes[number] = value as f32 / i16::MAX as f32
+                        }
+                        _ => {}
+                    }
+                }
+                Err(error) if error.kind() == std::io::ErrorKind::WouldBlock => return,
+                // Unplugged, most likely.
+                Err(_) => {
+                    self.file = None;
+                    self.axes = neutral_axes();
+                    self.buttons = [false; BUTTON_COUNT];
+                    return;
+                }
+            }
+        }
+    }
+
+    pub fn value(&self, control: PadControl) -> f32 {
+        control.value_of(&self.axes, &self.buttons)
+    }
+
+    pub fn is_down(&self, control: PadControl) -> bool {
+        self.value(control) >= ACTIVE_THRESHOLD
+    }
+
+    // Only true on the poll it went from up to down.
+    pub fn is_pressed(&self, control: PadControl) -> bool {
+        self.is_down(control)
+            && control.value_of(&self.previous_axes, &self.previous_buttons) < ACTIVE_THRESHOLD
+    }
+
+    // Whatever was just pressed, for binding things in the controls menu.
+    pub fn last_pressed(&self) -> Option<PadControl> {
+        PadControl::ALL
+            .into_iter()
+            .find(|&control| self.is_pressed(control))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nothing_pushed_at_rest() {
+        let (axes, buttons) = (neutral_axes(), [false; BUTTON_COUNT]);
+        for control in PadControl::ALL {
+            assert_eq!(control.value_of(&axes, &buttons), 0.0, "{:?}", control);
+        }
+        let gamepad = Gamepad::default();
+        assert_eq!(gamepad.value(PadControl::LeftTrigger), 0.0);
+        assert!(!gamepad.is_pressed(PadControl::RightTrigger));
+    }
+
+    #[test]
+    fn triggers_and_stick_deadzone() {
+        let buttons = [false; BUTTON_COUNT];
+        let mut axes = neutral_axes();
+        axes[2] = 1.0;
+        axes[5] = 0.0;
+        assert_eq!(PadControl::LeftTrigger.value_of(&axes, &buttons), 1.0);
+        assert_eq!(PadControl::RightTrigger.value_of(&axes, &buttons), 0.5);
+
+        axes[0] = DEADZONE * 0.9;
+        assert_eq!(PadControl::StickRight.value_of(&axes, &buttons), 0.0);
+        axes[0] = -1.0;
+        assert_eq!(PadControl::StickLeft.value_of(&axes, &buttons), 1.0);
+        assert_eq!(PadControl::StickRight.value_of(&axes, &buttons), 0.0);
+        axes[1] = -(DEADZONE + 1.0) / 2.0;
+        assert!((PadControl::StickUp.value_of(&axes, &buttons) - 0.5).abs() < 1e-6);
+    }
+}
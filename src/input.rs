@@ -0,0 +1,498 @@
+use std::io::{Error, ErrorKind};
+use std::path::Path;
+
+use macroquad::input::{is_key_down, is_key_pressed, KeyCode};
+
+use crate::game::Inputs;
+use crate::gamepad::{Gamepad, PadControl};
+
+pub const CONTROLS_FILE: &str = "controls.cfg"; // Where rebound controls get saved.
+
+// Everything the player can ask for, no matter what they pressed to ask.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    Thrust,
+    RotateLeft,
+    RotateRight,
+    Brake,
+    Fire,
+    Hyperspace,
+    Shield,
+    Restart,
+    Quit,
+    Pause,
+}
+
+impl Action {
+    pub const ALL: [Action; 10] = [
+        Action::Thrust,
+        Action::RotateLeft,
+        Action::RotateRight,
+        Action::Brake,
+        Action::Fire,
+        Action::Hyperspace,
+        Action::Shield,
+        Action::Restart,
+        Action::Quit,
+        Action::Pause,
+    ];
+
+    // What it's called in the config file.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Action::Thrust => "thrust",
+            Action::RotateLeft => "rotate_left",
+            Action::RotateRight => "rotate_right",
+            Action::Brake => "brake",
+            Action::Fire => "fire",
+            Action::Hyperspace => "hyperspace",
+            Action::Shield => "shield",
+            Action::Restart => "restart",
+            Action::Quit => "quit",
+            Action::Pause => "pause",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Action> {
+        Action::ALL.into_iter().find(|action| action.name() == name)
+    }
+}
+
+// Spelling out key names both ways, since KeyCode can't do it itself.
+macro_rules! key_names {
+    ($($key:ident),* $(,)?) => {
+        pub fn key_name(key: KeyCode) -> Option<&'static str> {
+            match key {
+                $(KeyCode::$key => Some(stringify!($key)),)*
+                _ => None,
+            }
+        }
+
+        pub fn key_from_name(name: &str) -> Option<KeyCode> {
+            match name {
+                $(stringify!($key) => Some(KeyCode::$key),)*
+                _ => None,
+            }
+        }
+    };
+}
+
+key_names!(
+    A,
+    B,
+    C,
+    D,
+    E,
+    F,
+    G,
+    H,
+    I,
+    J,
+    K,
+    L,
+    M,
+    N,
+    O,
+    P,
+    Q,
+    R,
+    S,
+    T,
+    U,
+    V,
+    W,
+    X,
+    Y,
+    Z,
+    Key0,
+    Key1,
+    Key2,
+    Key3,
+    Key4,
+    Key5,
+    Key6,
+    Key7,
+    Key8,
+    Key9,
+    Space,
+    Enter,
+    Escape,
+    Tab,
+    Backspace,
+    Up,
+    Down,
+    Left,
+    Right,
+    LeftShift,
+    RightShift,
+    LeftControl,
+    RightControl,
+    LeftAlt,
+    RightAlt,
+    LeftSuper,
+    RightSuper,
+    Comma,
+    Period,
+    Slash,
+    Semicolon,
+    Minus,
+    Equal,
+    Apostrophe,
+    LeftBracket,
+    RightBracket,
+    Backslash,
+    GraveAccent,
+    Insert,
+    Delete,
+    Home,
+    End,
+    PageUp,
+    PageDown,
+    CapsLock,
+    ScrollLock,
+    NumLock,
+    PrintScreen,
+    Pause,
+    Menu,
+    Kp0,
+    Kp1,
+    Kp2,
+    Kp3,
+    Kp4,
+    Kp5,
+    Kp6,
+    Kp7,
+    Kp8,
+    Kp9,
+    KpDecimal,
+    KpDivide,
+    KpMultiply,
+    KpSubtract,
+    KpAdd,
+    KpEnter,
+    KpEqual,
+    F1,
+    F2,
+    F3,
+    F4,
+    F5,
+    F6,
+    F7,
+    F8,
+    F9,
+    F10,
+    F11,
+    F12,
+);
+
+// Something physical an action can be bound to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Binding {
+    Key(KeyCode),
+    Pad(PadControl),
+}
+
+impl Binding {
+    pub fn name(&self) -> Option<&'static str> {
+        match self {
+            Binding::Key(key) => key_name(*key),
+            Binding::Pad(control) => Some(control.name()),
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Binding> {
+        key_from_name(name)
+            .map(Binding::Key)
+            .or_else(|| PadControl::from_name(name).map(Binding::Pad))
+    }
+
+    // How far it's pushed, from 0 to 1. Keys are either all the way or not at all.
+    pub fn value(&self, gamepad: &Gamepad) -> f32 {
+        match self {
+            Binding::Key(key) => is_key_down(*key) as u32 as f32,
+            Binding::Pad(control) => gamepad.value(*control),
+        }
+    }
+
+    pub fn is_pressed(&self, gamepad: &Gamepad) -> bool {
+        match self {
+            Binding::Key(key) => is_key_pressed(*key),
+            Binding::Pad(control) => gamepad.is_pressed(*control),
+        }
+    }
+}
+
+// Which keys and pad controls do which actions. Any of an action's bindings will do.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InputMap {
+    bindings: Vec<(Action, Vec<Binding>)>,
+}
+
+impl Default for InputMap {
+    fn default() -> Self {
+        use Binding::{Key, Pad};
+        Self {
+            bindings: vec![
+                (
+                    Action::Thrust,
+                    vec![Key(KeyCode::W), Pad(PadControl::StickUp)],
+                ),
+                (
+                    Action::RotateLeft,
+                    vec![Key(KeyCode::A), Pad(PadControl::StickLeft)],
+                ),
+                (
+                    Action::RotateRight,
+                    vec![Key(KeyCode::D), Pad(PadControl::StickRight)],
+                ),
+                (
+                    Action::Brake,
+                    vec![Key(KeyCode::S), Pad(PadControl::LeftTrigger)],
+                ),
+                (
+                    Action::Fire,
+                    vec![
+                        Key(KeyCode::Space),
+                        Pad(PadControl::RightTrigger),
+                        Pad(PadControl::South),
+                    ],
+                ),
+                (
+                    Action::Hyperspace,
+                    vec![Key(KeyCode::LeftShift), Pad(PadControl::East)],
+                ),
+                (Action::Shield, vec![Key(KeyCode::E), Pad(PadControl::West)]),
+                (
+                    Action::Restart,
+                    vec![Key(KeyCode::R), Pad(PadControl::Start)],
+                ),
+                (
+                    Action::Quit,
+                    vec![Key(KeyCode::Escape), Pad(PadControl::Select)],
+                ),
+                (Action::Pause, vec![Key(KeyCode::P), Pad(PadControl::North)]),
+            ],
+        }
+    }
+}
+
+impl InputMap {
+    pub fn bindings(&self, action: Action) -> &[Binding] {
+        self.bindings
+            .iter()
+            .find(|(bound, _)| *bound == action)
+            .map(|(_, bindings)| bindings.as_slice())
+            .unwrap_or(&[])
+    }
+
+    fn bindings_mut(&mut self, action: Action) -> &mut Vec<Binding> {
+        let index = match self.bindings.iter().position(|(bound, _)| *bound == action) {
+            Some(index) => index,
+            None => {
+                self.bindings.push((action, Vec::new()));
+                self.bindings.len() - 1
+            }
+        };
+        &mut self.bindings[index].1
+    }
+
+    // Adding another binding to an action, if it isn't already there.
+    pub fn bind(&mut self, action: Action, binding: Binding) {
+        let bindings = self.bindings_mut(action);
+        if !bindings.contains(&binding) {
+            bindings.push(binding);
+        }
+    }
+
+    pub fn clear(&mut self, action: Action) {
+        self.bindings_mut(action).clear();
+    }
+
+    // The keys for an action, written out for the HUD. Pad controls are left out to keep it short.
+    pub fn label(&self, action: Action) -> String {
+        self.bindings(action)
+            .iter()
+            .filter_map(|binding| match binding {
+                Binding::Key(key) => key_name(*key),
+                Binding::Pad(_) => None,
+            })
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+
+    // Every binding for an action, pad controls and all, for the controls menu.
+    pub fn describe(&self, action: Action) -> String {
+        self.bindings(action)
+            .iter()
+            .filter_map(Binding::name)
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    // These need a window, so only the binary gets to call them.
+    pub fn value(&self, action: Action, gamepad: &Gamepad) -> f32 {
+        self.bindings(action)
+            .iter()
+            .map(|binding| binding.value(gamepad))
+            .fold(0.0, f32::max)
+    }
+
+    pub fn is_pressed(&self, action: Action, gamepad: &Gamepad) -> bool {
+        self.bindings(action)
+            .iter()
+            .any(|binding| binding.is_pressed(gamepad))
+    }
+
+    // What the ship gets told this tick. Fire is passed in, since presses get held onto between ticks.
+    pub fn inputs(&self, gamepad: &Gamepad, fire: bool, hyperspace: bool) -> Inputs {
+        Inputs {
+            thrust: self.value(Action::Thrust, gamepad),
+            rotate: self.value(Action::RotateRight, gamepad)
+                - self.value(Action::RotateLeft, gamepad),
+            brake: self.value(Action::Brake, gamepad),
+            fire,
+            hyperspace,
+            shield: self.value(Action::Shield, gamepad) > 0.5,
+        }
+        .quantized()
+    }
+
+    // Reading `action = Key, PadControl` lines. Anything left out keeps its default.
+    pub fn parse(config: &str) -> std::io::Result<Self> {
+        let mut input_map = Self::default();
+        for (line_number, line) in config.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let invalid = |message: String| {
+                Error::new(
+                    ErrorKind::InvalidData,
+                    format!("line {}: {}", line_number + 1, message),
+                )
+            };
+            let (name, bindings) = line
+                .split_once('=')
+                .ok_or_else(|| invalid("expected `action = Key, Key`".to_string()))?;
+            let action = Action::from_name(name.trim())
+                .ok_or_else(|| invalid(format!("unknown action `{}`", name.trim())))?;
+            input_map.clear(action);
+            for name in bindings
+                .split(',')
+                .map(str::trim)
+                .filter(|name| !name.is_empty())
+            {
+                let binding = Binding::from_name(name)
+                    .ok_or_else(|| invalid(format!("unknown key `{}`", name)))?;
+                input_map.bind(action, binding);
+            }
+        }
+        Ok(input_map)
+    }
+
+    pub fn to_config_string(&self) -> String {
+        let mut config = String::new();
+        for action in Action::ALL {
+            let names = self
+                .bindings(action)
+                .iter()
+                .filter_map(Binding::name)
+                .collect::<Vec<_>>();
+            config.push_str(&format!("{} = {}\n", action.name(), names.join(", ")));
+        }
+        config
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        Self::parse(&std::fs::read_to_string(path)?)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> std::io::Result<()> {
+        std::fs::write(path, self.to_config_string())
+    }
+}
+
+// The in-game menu for rebinding things. Drawing it is the binary's problem.
+#[derive(Debug, Clone, Default)]
+pub struct ControlsMenu {
+    pub open: bool,
+    pub selected: usize, // Which action is highlighted.
+    pub listening: bool, // Waiting on the next key to bind.
+}
+
+impl ControlsMenu {
+    pub fn selected_action(&self) -> Action {
+        Action::ALL[self.selected]
+    }
+
+    pub fn move_selection(&mut self, offset: i32) {
+        let count = Action::ALL.len() as i32;
+        self.selected = (self.selected as i32 + offset).rem_euclid(count) as usize;
+    }
+
+    // Handing over a key or pad control while listening, which binds it to the selected action.
+    // Escape backs out instead, and anything without a name is ignored, since it couldn't be saved.
+    pub fn bind(&mut self, input_map: &mut InputMap, binding: Binding) {
+        if !self.listening {
+            return;
+        }
+        if binding == Binding::Key(KeyCode::Escape) {
+            self.listening = false;
+        } else if binding.name().is_some() {
+            input_map.bind(self.selected_action(), binding);
+            self.listening = false;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn config_round_trips() {
+        let mut input_map = InputMap::default();
+        input_map.clear(Action::Fire);
+        input_map.bind(Action::Fire, Binding::Key(KeyCode::Kp0));
+        input_map.bind(Action::Fire, Binding::Pad(PadControl::South));
+        assert_eq!(
+            InputMap::parse(&input_map.to_config_string()).unwrap(),
+            input_map
+        );
+        assert_eq!(
+            InputMap::parse(&InputMap::default().to_config_string()).unwrap(),
+            InputMap::default()
+        );
+    }
+
+    #[test]
+    fn bad_lines_say_where() {
+        let error = InputMap::parse("fire = Space\nthrust = NotAKey\n").unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+        assert!(error.to_string().starts_with("line 2:"));
+    }
+
+    #[test]
+    fn menu_escape_cancels_and_unnamed_keys_wait() {
+        let mut input_map = InputMap::default();
+        let mut menu = ControlsMenu {
+            open: true,
+            listening: true,
+            ..Default::default()
+        };
+        let before = input_map.bindings(menu.selected_action()).to_vec();
+        menu.bind(&mut input_map, Binding::Key(KeyCode::F20));
+        assert!(menu.listening);
+        menu.bind(&mut input_map, Binding::Key(KeyCode::Escape));
+        assert!(!menu.listening);
+        assert_eq!(input_map.bindings(menu.selected_action()), before);
+
+        menu.listening = true;
+        menu.bind(&mut input_map, Binding::Key(KeyCode::Home));
+        assert!(!menu.listening);
+        assert!(InputMap::parse(&input_map.to_config_string())
+            .unwrap()
+            .bindings(menu.selected_action())
+            .contains(&Binding::Key(KeyCode::Home)));
+    }
+}
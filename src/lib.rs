@@ -0,0 +1,63 @@
+pub mod broadphase;
+pub mod collision;
+pub mod combo;
+pub mod components;
+pub mod difficulty;
+pub mod game;
+pub mod gamepad;
+pub mod input;
+pub mod narrowphase;
+pub mod particles;
+pub mod playfield;
+pub mod popup;
+pub mod powerup;
+pub mod replay;
+pub mod respawn;
+pub mod rng;
+pub mod saucer;
+pub mod shield;
+pub mod timestep;
+pub mod variant;
+pub mod wave;
+
+pub use broadphase::SpatialHash;
+pub use collision::CollisionEvent;
+pub use combo::{Combo, RunStats};
+pub use difficulty::{Curve, Difficulty};
+pub use game::{Game, Inputs, Sound};
+pub use gamepad::{Gamepad, PadControl};
+pub use input::{Action, Binding, ControlsMenu, InputMap};
+pub use particles::{Particle, ParticleStorage};
+pub use playfield::Playfield;
+pub use popup::ScorePopup;
+pub use replay::{Playback, Replay};
+pub use rng::Rng;
+pub use timestep::{interpolate_position, FixedTimestep};
+
+// Vars
+pub const DISPLAY_TARGET_WIDTH: u32 = 256; // The width we want.
+pub const DISPLAY_TARGET_HEIGHT: u32 = 144; // The height we want.
+
+// Helper functions.
+pub fn square_distance(x1: f32, y1: f32, x2: f32, y2: f32) -> f32 {
+    (x1 - x2).powf(2.0) + (y1 - y2).powf(2.0)
+}
+
+pub fn deg2rad(degrees: f32) -> f32 {
+    degrees * std::f32::consts::PI / 180.0
+}
+
+pub fn rad2deg(radians: f32) -> f32 {
+    radians * 180.0 / std::f32::consts::PI
+}
+
+pub fn normalize_point(point: (f32, f32)) -> (f32, f32) {
+    let sum = point.0 + point.1;
+    (point.0 / sum, point.1 / sum)
+}
+
+pub fn rotate_point(point: (f32, f32), degrees: f32) -> (f32, f32) {
+    let sin = deg2rad(degrees).sin();
+    let cos = deg2rad(degrees).cos();
+    (point.0 * cos - point.1 * sin, point.0 * sin + point.1 * cos)
+}
@@ -1,15 +1,16 @@
-use hecs::*;
+use cmps_455_3_asteroids::*;
 use macroquad::prelude::*;
+#[cfg(feature = "audio")]
 use rodio::*;
 
-mod components;
-
 // Vars
-const DISPLAY_TARGET_WIDTH: u32 = 256; // The width we want.
-const DISPLAY_TARGET_HEIGHT: u32 = 144; // The height we want.
 const DEBUG_ENABLED: bool = false; // For debug view.
+#[cfg(feature = "audio")]
 const MAX_VOLUME: f32 = 0.1;
+const TICK_RATE: f32 = timestep::DEFAULT_TICK_RATE; // How many times a second the simulation runs.
+const REPLAY_DIRECTORY: &str = "replays"; // Where recorded runs end up.
 
+#[cfg(feature = "audio")]
 macro_rules! play_audio {
     ($sink:ident, $file:expr $(,)?, $volume:expr $(,)?, $speed:expr $(,)?) => {
         $sink.skip_one();
@@ -22,165 +23,133 @@ macro_rules! play_audio {
     };
 }
 
-// Helper functions.
-pub fn square_distance(x1: f32, y1: f32, x2: f32, y2: f32) -> f32 {
-    (x1 - x2).powf(2.0) + (y1 - y2).powf(2.0)
-}
-
-pub fn deg2rad(degrees: f32) -> f32 {
-    degrees * std::f32::consts::PI / 180.0
-}
-
-pub fn rad2deg(radians: f32) -> f32 {
-    radians * 180.0 / std::f32::consts::PI
-}
-
-pub fn normalize_point(point: (f32, f32)) -> (f32, f32) {
-    let sum = point.0 + point.1;
-    (point.0 / sum, point.1 / sum)
-}
-
-pub fn rotate_point(point: (f32, f32), degrees: f32) -> (f32, f32) {
-    let sin = deg2rad(degrees).sin();
-    let cos = deg2rad(degrees).cos();
-    (point.0 * cos - point.1 * sin, point.0 * sin + point.1 * cos)
-}
-
-fn create_asteroid(world: &mut World) {
-    let time = get_time() as f32 * 128.0;
-    create_asteroid_point(
-        world,
-        (
-            DISPLAY_TARGET_WIDTH as f32 * time.cos() + DISPLAY_TARGET_WIDTH as f32 / 2.0,
-            DISPLAY_TARGET_HEIGHT as f32 * time.sin() + DISPLAY_TARGET_HEIGHT as f32 / 2.0,
-        ),
-        (rand::rand() % 400) as f32 / 100.0 + 3.0,
-    );
-}
-
-fn create_asteroid_point(world: &mut World, point: (f32, f32), size: f32) {
-    let mut rock_shape = (0..16)
-        .map(|x| rotate_point((0.0, (rand::rand() % 2) as f32 + size), x as f32 * 22.5))
-        .collect::<Vec<(f32, f32)>>();
-    rock_shape.push(rock_shape[0]);
-    world.spawn((
-        components::Position(point.0, point.1, 0.0),
-        components::Velocity(
-            (rand::rand() % 20) as f32 - 10.0,
-            (rand::rand() % 20) as f32 - 10.0,
-            (rand::rand() % 20) as f32 - 10.0,
-        ),
-        components::Draw(
-            Color {
-                r: 0.7,
-                g: 0.7,
-                b: 0.7,
-                a: 1.0,
-            },
-            rock_shape,
-        ),
-        components::Collidable(size, components::CollidableType::ASTEROID),
-    ));
-}
-
-// Particle Sutff for obvi reasons.
-//
-#[derive(Default, Clone, Copy)]
-struct Particle {
-    position: (f32, f32),
-    velocity: (f32, f32),
-    drag: f32,
-    size: f32,
-    color: Color,
-    birthtime: f64,
-    deathtime: f64,
+// Playing whatever the simulation asked for.
+#[cfg(feature = "audio")]
+fn play_sound(sink_sfx: &Sink, rng: &mut Rng, sound: Sound) {
+    match sound {
+        Sound::PlayerSpawn => {
+            play_audio!(
+                sink_sfx,
+                "assets/sfx/PlayerSpawn.wav",
+                0.8,
+                (rng.rand() % 100 / 100) as f32 + 0.9
+            );
+        }
+        Sound::PlayerShoot => {
+            play_audio!(
+                sink_sfx,
+                "assets/sfx/PlayerShoot.wav",
+                0.15,
+                (rng.rand() % 100 / 1000) as f32 + 0.9
+            );
+        }
+        Sound::PlayerDeathRock => {
+            play_audio!(
+                sink_sfx,
+                "assets/sfx/PlayerDeathRock.wav",
+                0.7,
+                (rng.rand() % 100 / 100) as f32 + 0.9
+            );
+        }
+        Sound::PlayerDeathLaser => {
+            play_audio!(
+                sink_sfx,
+                "assets/sfx/PlayerDeathLaser.wav",
+                0.7,
+                (rng.rand() % 100 / 100) as f32 + 0.9
+            );
+        }
+        Sound::AsteroidExplode => {
+            play_audio!(
+                sink_sfx,
+                "assets/sfx/AsteroidExplode.wav",
+                0.2,
+                (rng.rand() % 100 / 1000) as f32 + 0.9
+            );
+        }
+        Sound::Hyperspace => {
+            play_audio!(
+                sink_sfx,
+                "assets/sfx/Hyperspace.wav",
+                0.4,
+                (rng.rand() % 100 / 1000) as f32 + 0.9
+            );
+        }
+        // Saucers make do with deeper versions of what we've got.
+        Sound::SaucerShoot => {
+            play_audio!(
+                sink_sfx,
+                "assets/sfx/PlayerShoot.wav",
+                0.12,
+                (rng.rand() % 100 / 1000) as f32 + 0.6
+            );
+        }
+        Sound::SaucerExplode => {
+            play_audio!(
+                sink_sfx,
+                "assets/sfx/AsteroidExplode.wav",
+                0.3,
+                (rng.rand() % 100 / 1000) as f32 + 0.6
+            );
+        }
+        // No pitch wobble on this one, it's a tune.
+        Sound::ExtraLife => {
+            play_audio!(sink_sfx, "assets/sfx/ExtraLife.wav", 0.5, 1.0);
+        }
+        // Pickups make do with a quicker take on the same tune.
+        Sound::PowerUp => {
+            play_audio!(sink_sfx, "assets/sfx/ExtraLife.wav", 0.3, 1.5);
+        }
+        Sound::ShieldHit => {
+            play_audio!(
+                sink_sfx,
+                "assets/sfx/PlayerShoot.wav",
+                0.2,
+                (rng.rand() % 100 / 1000) as f32 + 0.4
+            );
+        }
+    }
 }
 
-#[derive(Default, Clone)]
-struct ParticleStorage {
-    particles_container: Vec<Particle>,
+// Grabbing the value after a `--flag` on the command line, if there is one.
+fn arg_value(flag: &str) -> Option<String> {
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == flag {
+            return args.next();
+        }
+    }
+    None
 }
 
-impl ParticleStorage {
-    fn new() -> Self {
-        Self {
-            particles_container: Vec::new(),
-        }
+// Picking a seed, either the one from `--seed <number>` or one off the clock.
+fn pick_seed() -> u64 {
+    if let Some(seed) = arg_value("--seed").and_then(|seed| seed.parse().ok()) {
+        return seed;
     }
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|duration| duration.as_nanos() as u64)
+        .unwrap_or(0)
+}
 
-    fn create_particle(
-        &mut self,
-        count: i32,
-        position: (f32, f32),
-        velocity: (f32, f32),
-        drag: f32,
-        size: f32,
-        color: Color,
-        age: f64,
-        position_variance: (f32, f32),
-        velocity_variance: (f32, f32),
-        size_variance: f32,
-        age_variance: f64,
-    ) {
-        let curr_time = macroquad::time::get_time();
-        for _i in 0..count {
-            self.particles_container.push(Particle {
-                position: (
-                    position.0
-                        + rand::RandomRange::gen_range(-position_variance.0, position_variance.0),
-                    position.1
-                        + rand::RandomRange::gen_range(-position_variance.1, position_variance.1),
-                ),
-                velocity: (
-                    velocity.0
-                        + rand::RandomRange::gen_range(-velocity_variance.0, velocity_variance.0),
-                    velocity.1
-                        + rand::RandomRange::gen_range(-velocity_variance.1, velocity_variance.1),
-                ),
-                drag: drag,
-                size: size + rand::RandomRange::gen_range(-size_variance, size_variance),
-                color: color,
-                birthtime: curr_time,
-                deathtime: curr_time
-                    + age
-                    + rand::RandomRange::gen_range(-age_variance, age_variance),
+// Writing out whatever run we were recording, if any.
+fn save_recording(recording: &mut Option<Replay>) {
+    if let Some(replay) = recording.take() {
+        // Counting up past any earlier runs on the same seed, so restarting with --seed doesn't write over them.
+        let path = (1..)
+            .map(|run| {
+                std::path::Path::new(REPLAY_DIRECTORY).join(format!("{}-{}.rep", replay.seed, run))
             })
+            .find(|path| !path.exists())
+            .unwrap();
+        match std::fs::create_dir_all(REPLAY_DIRECTORY).and_then(|_| replay.save(&path)) {
+            Ok(()) => println!("Saved replay to {}", path.display()),
+            Err(error) => eprintln!("Couldn't save replay to {}: {}", path.display(), error),
         }
     }
 }
 
-// This, too, is yuri.
-fn world_reset(world: &mut World) {
-    world.clear(); // Resetting the world.
-                   // Our left paddle.
-                   // Getting our new things in.
-    world.spawn((
-        components::Position(
-            DISPLAY_TARGET_WIDTH as f32 / 2.0,
-            DISPLAY_TARGET_HEIGHT as f32,
-            0.0,
-        ),
-        components::Velocity(0.0, 20.0, 1125.0),
-        components::Draw(
-            Color {
-                r: 1.0,
-                g: 1.0,
-                b: 1.0,
-                a: 1.0,
-            },
-            vec![
-                (-2.0, 3.0),
-                (0.0, -3.0),
-                (2.0, 3.0),
-                (0.0, 1.0),
-                (-2.0, 3.0),
-            ],
-        ),
-        components::Collidable(1.0, components::CollidableType::PLAYER),
-        components::Controllable(),
-    ));
-}
-
 // Window Stuff
 //
 
@@ -201,56 +170,162 @@ async fn main() {
     let render_target = render_target(DISPLAY_TARGET_WIDTH, DISPLAY_TARGET_HEIGHT); // Setting our internal window size.
     render_target.texture.set_filter(FilterMode::Nearest); // And we love some nearest rendering.
 
-    // For tracking vars and the fun sorta things.
-    let mut hitstun: u32 = 0; // Yeah, the funny little hitstun thing returns.
-    let mut high_score: u32 = 0; // And a high score?
-    let mut score: u32 = 0; // Scooore!
-    let mut lives: u32 = 0; // ...And a new one, lives.
-
-    // Item management.
-    let mut clear_screen: bool = false;
-    let mut asteroid_cooldown: f32 = 0.0;
-
-    // Musics and things.
+    // Musics and things, unless we were built without the audio feature.
+    #[cfg(feature = "audio")]
     let (_stream, stream_handle) = OutputStream::try_default().unwrap(); // Creating our sinks.
+    #[cfg(feature = "audio")]
     let sink_music = Sink::try_new(&stream_handle).unwrap(); // Nothing fancy this time.
+    #[cfg(feature = "audio")]
     let sink_sfx = Sink::try_new(&stream_handle).unwrap(); // And SFX!
-    let mut target_volume_music; // Mhm.
+    #[cfg(feature = "audio")]
     let mut current_volume_music = 0.0; // Sure.
 
-    // For Hecs
-    let mut world = World::new();
-    let mut particles = ParticleStorage::new();
+    // The simulation itself.
+    let mut game = Game::new(pick_seed());
+    #[cfg(feature = "audio")]
+    let mut audio_rng = Rng::stream(game.seed, rng::STREAM_AUDIO); // Pitch jitter shouldn't touch gameplay.
+    let mut timestep = FixedTimestep::new(TICK_RATE);
+    let mut fire_pressed = false; // Holding onto presses until a tick actually gets to see them.
+    let mut hyperspace_pressed = false;
+    let mut paused = false;
+
+    // Controls, and the menu for changing them.
+    let mut input_map = match InputMap::load(input::CONTROLS_FILE) {
+        Ok(input_map) => input_map,
+        Err(error) => {
+            if error.kind() != std::io::ErrorKind::NotFound {
+                eprintln!("Couldn't load {}: {}", input::CONTROLS_FILE, error);
+            }
+            InputMap::default()
+        }
+    };
+    let mut controls_menu = ControlsMenu::default();
+
+    // The difficulty presets, plus whatever `--difficulty` asked for if it's not one of them.
+    let mut difficulties = Difficulty::presets();
+    let mut selected_difficulty = 1; // Normal.
+    if let Some(arg) = arg_value("--difficulty") {
+        match Difficulty::from_arg(&arg) {
+            Ok(difficulty) => {
+                selected_difficulty = match difficulties.iter().position(|d| *d == difficulty) {
+                    Some(index) => index,
+                    None => {
+                        difficulties.push(difficulty);
+                        difficulties.len() - 1
+                    }
+                };
+            }
+            Err(error) => eprintln!("Couldn't load difficulty {}: {}", arg, error),
+        }
+    }
+    let mut gamepad = Gamepad::open();
+
+    // Replays, either the one we're recording or the one we're watching.
+    let mut recording: Option<Replay> = None;
+    let mut playback: Option<Playback> = match arg_value("--replay") {
+        Some(path) => match Replay::load(&path) {
+            Ok(replay) => {
+                game.difficulty = replay.difficulty.clone();
+                game.restart(replay.seed);
+                #[cfg(feature = "audio")]
+                {
+                    audio_rng = Rng::stream(game.seed, rng::STREAM_AUDIO);
+                }
+                timestep = FixedTimestep::new(replay.tick_rate);
+                Some(Playback::new(replay))
+            }
+            Err(error) => {
+                eprintln!("Couldn't load replay {}: {}", path, error);
+                None
+            }
+        },
+        None => None,
+    };
 
     'running: loop {
         // And important vars.
-        let current_time = macroquad::time::get_time();
         let delta_time = macroquad::time::get_frame_time();
 
-        // drawing to the texture
-        if is_key_pressed(KeyCode::Escape) {
-            break 'running;
-        }
-        if is_key_pressed(KeyCode::R) {
-            world_reset(&mut world);
-            high_score = high_score.max(score);
-            score = 0;
-            lives = 3;
-            play_audio!(
-                sink_sfx,
-                "assets/sfx/PlayerSpawn.wav",
-                0.8,
-                (rand::rand() % 100 / 100) as f32 + 0.9
-            );
+        gamepad.poll();
+
+        // CONTROLS MENU
+        //
+        if controls_menu.open {
+            if controls_menu.listening {
+                if let Some(key) = get_last_key_pressed() {
+                    controls_menu.bind(&mut input_map, Binding::Key(key));
+                } else if let Some(control) = gamepad.last_pressed() {
+                    controls_menu.bind(&mut input_map, Binding::Pad(control));
+                }
+            } else if is_key_pressed(KeyCode::Tab) {
+                controls_menu.open = false;
+                if let Err(error) = input_map.save(input::CONTROLS_FILE) {
+                    eprintln!("Couldn't save {}: {}", input::CONTROLS_FILE, error);
+                }
+            } else if is_key_pressed(KeyCode::Up) {
+                controls_menu.move_selection(-1);
+            } else if is_key_pressed(KeyCode::Down) {
+                controls_menu.move_selection(1);
+            } else if is_key_pressed(KeyCode::Enter) {
+                controls_menu.listening = true;
+            } else if is_key_pressed(KeyCode::Backspace) {
+                input_map.clear(controls_menu.selected_action());
+            }
+        } else {
+            if is_key_pressed(KeyCode::Tab) {
+                controls_menu.open = true;
+            }
+            // Picking a difficulty on the title screen, for the next run.
+            if game.lives == 0 && playback.is_none() {
+                let count = difficulties.len();
+                if is_key_pressed(KeyCode::Left) {
+                    selected_difficulty = (selected_difficulty + count - 1) % count;
+                } else if is_key_pressed(KeyCode::Right) {
+                    selected_difficulty = (selected_difficulty + 1) % count;
+                }
+            }
+            if input_map.is_pressed(Action::Quit, &gamepad) {
+                save_recording(&mut recording);
+                break 'running;
+            }
+            if input_map.is_pressed(Action::Pause, &gamepad) {
+                match &mut playback {
+                    Some(playback) => playback.paused = !playback.paused,
+                    None => paused = !paused,
+                }
+            }
+            if input_map.is_pressed(Action::Restart, &gamepad) {
+                match &mut playback {
+                    // Watching it again from the top.
+                    Some(playback) => {
+                        playback.rewind();
+                        game.difficulty = playback.replay.difficulty.clone();
+                        game.restart(playback.replay.seed);
+                    }
+                    // A fresh run, and a fresh recording of it.
+                    None => {
+                        save_recording(&mut recording);
+                        game.difficulty = difficulties[selected_difficulty].clone();
+                        game.restart(pick_seed());
+                        recording = Some(Replay::new(
+                            game.seed,
+                            timestep.tick_rate,
+                            game.difficulty.clone(),
+                        ));
+                        paused = false;
+                    }
+                }
+                #[cfg(feature = "audio")]
+                {
+                    audio_rng = Rng::stream(game.seed, rng::STREAM_AUDIO);
+                }
+            }
         }
 
         // HANDLING OUR DRAWING FUNCTIONS!
         // 0..100, 0..100 camera
         set_camera(&Camera2D {
-            zoom: vec2(
-                0.0001 * (DISPLAY_TARGET_HEIGHT as f32),
-                0.0001 * (DISPLAY_TARGET_WIDTH as f32),
-            ),
+            zoom: vec2(playfield::CAMERA_ZOOM.0, playfield::CAMERA_ZOOM.1),
             target: vec2(0.0, 0.0),
             render_target: Some(render_target.clone()),
             ..Default::default()
@@ -258,9 +333,10 @@ async fn main() {
 
         // MUSIC SYSTEM
         //
+        #[cfg(feature = "audio")]
         {
             // Updating target values.
-            target_volume_music = (lives > 0) as u32 as f32;
+            let target_volume_music = (game.lives > 0) as u32 as f32;
             // Updating current values.
             current_volume_music = (current_volume_music * 0.99) + (target_volume_music * 0.01);
             // Setting the sinks.
@@ -279,453 +355,77 @@ async fn main() {
 
         // PHYSICS SYSTEM
         //
-        {
-            // Woo, physics.
-            // That means mutable bs, but at least we're prepared this time.
-            // Once again, the Mario 64 thing, to give a faux sense of concurrence.
-            if hitstun <= 0 {
-                // The best time for some periodic functions
-                if asteroid_cooldown <= 0.0 {
-                    create_asteroid(&mut world);
-                    asteroid_cooldown = 2.0;
-                }
-                asteroid_cooldown -= delta_time;
-
-                for _i in 1..4 {
-                    // Prepping a destruction system.
-                    let mut entities_to_destroy: Vec<Entity> = Vec::new();
-                    let mut asteroids_to_create: Vec<(f32, f32, f32)> = Vec::new();
-                    let mut bullets_to_create: Vec<(f32, f32, f32, f32, f32)> = Vec::new();
-
-                    // Update velocities.
-                    for (_id, (position, velocity)) in
-                        world.query_mut::<(&mut components::Position, &components::Velocity)>()
-                    {
-                        position.0 = position.0 + velocity.0 * delta_time; // Updating X with the current velocity while also wrapping it.
-                        position.1 = position.1 + velocity.1 * delta_time; // Same for height.
-                        position.2 = (position.2 + velocity.2 * delta_time) % 360.0;
-                        // Same for degrees.
+        match &mut playback {
+            // Nothing moves while the controls are being fiddled with.
+            _ if controls_menu.open => {}
+            // Playing back a replay, with F to fast-forward and N to step while paused.
+            Some(playback) => {
+                let ticks = if playback.paused {
+                    is_key_pressed(KeyCode::N) as u32
+                } else if is_key_down(KeyCode::F) {
+                    timestep.advance(delta_time) * replay::FAST_FORWARD_SPEED
+                } else {
+                    timestep.advance(delta_time)
+                };
+                for _tick in 0..ticks {
+                    match playback.next_inputs() {
+                        Some(inputs) => game.step(&inputs, timestep.tick_length()),
+                        None => break,
                     }
-                    // Then get a collection of all possible, collidable objects.
-                    let collidable_objects = world
-                        .query::<(&components::Position, &components::Collidable)>()
-                        .iter()
-                        .map(|(e, (&p, &c))| (e, p, c))
-                        .collect::<Vec<_>>();
-
-                    for (id, (position, velocity, collidable)) in world.query_mut::<(
-                        &mut components::Position,
-                        &mut components::Velocity,
-                        &components::Collidable,
-                    )>() {
-                        for other in &collidable_objects {
-                            // Doing wrapping here, since we can detect the size of things.
-                            if position.0 > 200.0 + collidable.0 {
-                                position.0 = 55.0 - collidable.0
-                            }
-                            if position.0 < 55.0 - collidable.0 {
-                                position.0 = 200.0 + collidable.0
-                            }
-                            if position.1 > 115.0 + collidable.0 {
-                                position.1 = 30.0 - collidable.0
-                            }
-                            if position.1 < 30.0 - collidable.0 {
-                                position.1 = 115.0 + collidable.0
-                            }
-
-                            // Now performing collision checks.
-                            if id != other.0
-                                && square_distance(position.0, position.1, other.1 .0, other.1 .1)
-                                    < collidable.0.powf(2.0) + other.2 .0.powf(2.0)
-                            {
-                                // For our current collidable.
-                                match collidable.1 {
-                                    // If it's the player.
-                                    components::CollidableType::PLAYER => {
-                                        let mut damaged = false;
-                                        match other.2 .1 {
-                                            // We were hit by another player?
-                                            components::CollidableType::PLAYER => {}
-                                            // We were hit by an asteroid.
-                                            components::CollidableType::ASTEROID => {
-                                                particles.create_particle(
-                                                    16,
-                                                    (position.0, position.1),
-                                                    (0.0, 0.0),
-                                                    0.95,
-                                                    1.0,
-                                                    Color {
-                                                        r: 0.9,
-                                                        g: 0.9,
-                                                        b: 0.9,
-                                                        a: 1.0,
-                                                    },
-                                                    2.0,
-                                                    (0.0, 0.0),
-                                                    (50.0, 50.0),
-                                                    0.5,
-                                                    0.2,
-                                                );
-                                                damaged = true;
-                                                play_audio!(
-                                                    sink_sfx,
-                                                    "assets/sfx/PlayerDeathRock.wav",
-                                                    0.7,
-                                                    (rand::rand() % 100 / 100) as f32 + 0.9
-                                                );
-                                            }
-                                            // We were hit by a bullet.
-                                            components::CollidableType::BULLET => {
-                                                particles.create_particle(
-                                                    16,
-                                                    (position.0, position.1),
-                                                    (0.0, 0.0),
-                                                    0.95,
-                                                    1.0,
-                                                    Color {
-                                                        r: 0.9,
-                                                        g: 0.1,
-                                                        b: 0.1,
-                                                        a: 1.0,
-                                                    },
-                                                    2.0,
-                                                    (0.0, 0.0),
-                                                    (50.0, 50.0),
-                                                    0.5,
-                                                    0.2,
-                                                );
-                                                play_audio!(
-                                                    sink_sfx,
-                                                    "assets/sfx/PlayerDeathLaser.wav",
-                                                    0.7,
-                                                    (rand::rand() % 100 / 100) as f32 + 0.9
-                                                );
-                                                damaged = true;
-                                            }
-                                        }
-                                        if damaged {
-                                            lives -= 1;
-                                            hitstun += 64 / (1 + lives);
-                                            clear_screen = true;
-                                            if lives <= 0 {
-                                                entities_to_destroy.push(id);
-                                                // explode.
-                                            }
-                                        }
-                                    }
-                                    // If it's an asteroid.
-                                    components::CollidableType::ASTEROID => {
-                                        match other.2 .1 {
-                                            // We were hit by the player.
-                                            components::CollidableType::PLAYER => {}
-                                            // We were hit by another asteroid.
-                                            components::CollidableType::ASTEROID => {
-                                                velocity.0 = (position.0 - other.1 .0) / 2.0;
-                                                velocity.1 = (position.1 - other.1 .1) / 2.0;
-                                            }
-                                            // We were hit by a bullet.
-                                            components::CollidableType::BULLET => {
-                                                if collidable.0 > 3.0 {
-                                                    let new_vector = rotate_point(
-                                                        (collidable.0, 0.0),
-                                                        (rand::rand() % 360) as f32,
-                                                    );
-                                                    asteroids_to_create.push((
-                                                        position.0 + new_vector.0,
-                                                        position.1 + new_vector.0,
-                                                        collidable.0 - 1.0,
-                                                    ));
-                                                    asteroids_to_create.push((
-                                                        position.0 - new_vector.0,
-                                                        position.1 - new_vector.1,
-                                                        collidable.0 - 1.0,
-                                                    ));
-                                                }
-                                                particles.create_particle(
-                                                    16,
-                                                    (position.0, position.1),
-                                                    (0.0, 0.0),
-                                                    0.95,
-                                                    0.5,
-                                                    Color {
-                                                        r: 0.7,
-                                                        g: 0.7,
-                                                        b: 0.7,
-                                                        a: 1.0,
-                                                    },
-                                                    0.4,
-                                                    (0.0, 0.0),
-                                                    (30.0, 30.0),
-                                                    0.25,
-                                                    0.1,
-                                                );
-                                                play_audio!(
-                                                    sink_sfx,
-                                                    "assets/sfx/AsteroidExplode.wav",
-                                                    0.2,
-                                                    (rand::rand() % 100 / 1000) as f32 + 0.9
-                                                );
-                                                score += 1;
-                                                high_score = high_score.max(score);
-                                                entities_to_destroy.push(id);
-                                            }
-                                        }
-                                    }
-                                    // If it's a bullet.
-                                    components::CollidableType::BULLET => {
-                                        // Just explode if we hit anything.
-                                        particles.create_particle(
-                                            8,
-                                            (position.0, position.1),
-                                            (0.0, 0.0),
-                                            0.95,
-                                            0.5,
-                                            Color {
-                                                r: 0.7,
-                                                g: 0.0,
-                                                b: 0.0,
-                                                a: 1.0,
-                                            },
-                                            0.4,
-                                            (0.0, 0.0),
-                                            (10.0, 10.0),
-                                            0.25,
-                                            0.1,
-                                        );
-                                        entities_to_destroy.push(id);
-                                    }
-                                }
-                            }
-                        }
-                    }
-
-                    if clear_screen {
-                        for collidable in collidable_objects {
-                            match collidable.2 .1 {
-                                // If it's the player.
-                                components::CollidableType::PLAYER => {}
-                                // If it's an asteroid.
-                                components::CollidableType::ASTEROID => {
-                                    particles.create_particle(
-                                        16,
-                                        (collidable.1 .0, collidable.1 .1),
-                                        (0.0, 0.0),
-                                        0.95,
-                                        0.5,
-                                        Color {
-                                            r: 0.7,
-                                            g: 0.7,
-                                            b: 0.7,
-                                            a: 1.0,
-                                        },
-                                        0.4,
-                                        (0.0, 0.0),
-                                        (0.5, 0.5),
-                                        0.25,
-                                        0.1,
-                                    );
-                                    entities_to_destroy.push(collidable.0);
-                                }
-                                // If it's a bullet.
-                                components::CollidableType::BULLET => {
-                                    // Just explode if we hit anything.
-                                    particles.create_particle(
-                                        8,
-                                        (collidable.1 .0, collidable.1 .1),
-                                        (0.0, 0.0),
-                                        0.95,
-                                        0.5,
-                                        Color {
-                                            r: 0.7,
-                                            g: 0.0,
-                                            b: 0.0,
-                                            a: 1.0,
-                                        },
-                                        0.4,
-                                        (0.0, 0.0),
-                                        (0.5, 0.5),
-                                        0.25,
-                                        0.1,
-                                    );
-                                    entities_to_destroy.push(collidable.0);
-                                }
-                            }
-                        }
-                        clear_screen = false;
-                    }
-
-                    // Updating player controls.
-                    for (_id, (position, velocity, _controls)) in world.query_mut::<(
-                        &components::Position,
-                        &mut components::Velocity,
-                        &components::Controllable,
-                    )>() {
-                        let new_velocity = rotate_point((0.0, -6.0 * delta_time), position.2);
-                        velocity.0 =
-                            velocity.0 * (1.0 - is_key_down(KeyCode::S) as u32 as f32 * delta_time);
-                        velocity.1 =
-                            velocity.1 * (1.0 - is_key_down(KeyCode::S) as u32 as f32 * delta_time);
-                        velocity.2 =
-                            velocity.2 * (1.0 - is_key_down(KeyCode::S) as u32 as f32 * delta_time);
-
-                        velocity.0 = (velocity.0 * 0.995)
-                            + (new_velocity.0 * (is_key_down(KeyCode::W) as u32 as f32));
-                        velocity.1 = (velocity.1 * 0.995)
-                            + (new_velocity.1 * (is_key_down(KeyCode::W) as u32 as f32));
-                        velocity.2 = (velocity.2 * 0.975)
-                            + 135.0
-                                * delta_time
-                                * ((is_key_down(KeyCode::D) as u32 as f32)
-                                    - (is_key_down(KeyCode::A) as u32 as f32));
-
-                        if is_key_pressed(KeyCode::Space) {
-                            bullets_to_create
-                                .push((position.0, position.1, position.2, velocity.0, velocity.1));
-                            play_audio!(
-                                sink_sfx,
-                                "assets/sfx/PlayerShoot.wav",
-                                0.15,
-                                (rand::rand() % 100 / 1000) as f32 + 0.9
-                            );
-                        }
-                        if is_key_down(KeyCode::W) {
-                            let backwards = rotate_point((0.0, 4.0), position.2);
-                            particles.create_particle(
-                                1,
-                                (position.0 + backwards.0, position.1 + backwards.1),
-                                (backwards.0, backwards.1),
-                                1.0,
-                                1.0,
-                                Color {
-                                    r: 0.6,
-                                    g: 0.6,
-                                    b: 0.6,
-                                    a: 1.0,
-                                },
-                                1.0,
-                                (0.0, 0.0),
-                                (0.0, 0.0),
-                                0.0,
-                                0.2,
-                            );
-                        }
-                        if is_key_down(KeyCode::A) {
-                            let backwards = rotate_point((2.0, -2.0), position.2);
-                            particles.create_particle(
-                                1,
-                                (position.0 + backwards.0, position.1 + backwards.1),
-                                (backwards.0, backwards.1),
-                                1.0,
-                                1.0,
-                                Color {
-                                    r: 0.5,
-                                    g: 0.5,
-                                    b: 0.5,
-                                    a: 1.0,
-                                },
-                                0.7,
-                                (0.0, 0.0),
-                                (0.0, 0.0),
-                                0.0,
-                                0.2,
-                            );
-                        }
-                        if is_key_down(KeyCode::D) {
-                            let backwards = rotate_point((-2.0, -2.0), position.2);
-                            particles.create_particle(
-                                1,
-                                (position.0 + backwards.0, position.1 + backwards.1),
-                                (backwards.0, backwards.1),
-                                1.0,
-                                1.0,
-                                Color {
-                                    r: 0.5,
-                                    g: 0.5,
-                                    b: 0.5,
-                                    a: 1.0,
-                                },
-                                0.7,
-                                (0.0, 0.0),
-                                (0.0, 0.0),
-                                0.0,
-                                0.2,
-                            );
-                        }
-                    }
-
-                    // Destroying all things meant to be destroyed.
-                    entities_to_destroy.dedup();
-                    for entity in entities_to_destroy {
-                        // Let's just get its position and type to make an explosion effect.
-                        // Finally getting rid of it.
-                        match world.despawn(entity) {
-                            _ => {}
-                        }
-                    }
-
-                    // And making new things.
-                    asteroids_to_create.dedup();
-                    for asteroid in asteroids_to_create {
-                        create_asteroid_point(&mut world, (asteroid.0, asteroid.1), asteroid.2);
-                    }
-
-                    bullets_to_create.dedup();
-                    for bullet in bullets_to_create {
-                        let bullet_position = rotate_point((0.0, -2.0), bullet.2);
-                        let bulet_velocity = rotate_point((0.0, -20.0), bullet.2);
-                        world.spawn((
-                            components::Position(
-                                bullet.0 + bullet_position.0,
-                                bullet.1 + bullet_position.1,
-                                bullet.2,
-                            ),
-                            components::Velocity(
-                                bullet.3 + bulet_velocity.0,
-                                bullet.4 + bulet_velocity.1,
-                                0.0,
-                            ),
-                            components::Draw(
-                                Color {
-                                    r: 1.0,
-                                    g: 0.2,
-                                    b: 0.2,
-                                    a: 1.0,
-                                },
-                                vec![(0.0, -1.0), (0.0, 1.0)],
-                            ),
-                            components::Collidable(1.0, components::CollidableType::BULLET),
-                        ));
+                }
+            }
+            None if paused => {}
+            // Actually playing.
+            None => {
+                fire_pressed |= input_map.is_pressed(Action::Fire, &gamepad);
+                hyperspace_pressed |= input_map.is_pressed(Action::Hyperspace, &gamepad);
+                for _tick in 0..timestep.advance(delta_time) {
+                    let inputs = input_map.inputs(&gamepad, fire_pressed, hyperspace_pressed);
+                    fire_pressed = false;
+                    hyperspace_pressed = false;
+                    game.step(&inputs, timestep.tick_length());
+                    if let Some(recording) = &mut recording {
+                        recording.record(&inputs);
                     }
                 }
-            } else {
-                hitstun -= 1;
+                // The run's over, so it's time to write it down.
+                if game.lives == 0 {
+                    save_recording(&mut recording);
+                }
             }
         }
 
+        // SFX SYSTEM
+        //
+        #[cfg(feature = "audio")]
+        for sound in game.drain_sounds() {
+            play_sound(&sink_sfx, &mut audio_rng, sound);
+        }
+        #[cfg(not(feature = "audio"))]
+        game.drain_sounds();
+
         // DRAW SYSTEM
         //
         {
+            let alpha = timestep.alpha(); // How far between ticks we're drawing.
+
             // Clearing the background.
             clear_background(Color {
-                r: hitstun as f32 / 256.0,
-                g: hitstun as f32 / 256.0,
-                b: hitstun as f32 / 256.0,
+                r: game.hitstun * 60.0 / 256.0,
+                g: game.hitstun * 60.0 / 256.0,
+                b: game.hitstun * 60.0 / 256.0,
                 a: 1.0,
             });
 
             // Particles first, to render under everything.
-            particles.particles_container.iter_mut().for_each(|part| {
+            let current_time = game.particles.current_time;
+            game.particles.particles_container.iter().for_each(|part| {
+                let screen_position = game.playfield.to_screen(part.position);
                 draw_line(
-                    (part.position.0 % crate::DISPLAY_TARGET_WIDTH as f32)
-                        - (crate::DISPLAY_TARGET_WIDTH as f32 / 2.0),
-                    (part.position.1 % crate::DISPLAY_TARGET_HEIGHT as f32)
-                        - (crate::DISPLAY_TARGET_HEIGHT as f32 / 2.0),
-                    (part.position.0 % crate::DISPLAY_TARGET_WIDTH as f32)
-                        - (crate::DISPLAY_TARGET_WIDTH as f32 / 2.0)
-                        + (part.velocity.0 * part.size * 0.125),
-                    (part.position.1 % crate::DISPLAY_TARGET_HEIGHT as f32)
-                        - (crate::DISPLAY_TARGET_HEIGHT as f32 / 2.0)
-                        + (part.velocity.1 * part.size * 0.125),
+                    screen_position.0,
+                    screen_position.1,
+                    screen_position.0 + (part.velocity.0 * part.size * 0.125),
+                    screen_position.1 + (part.velocity.1 * part.size * 0.125),
                     clamp(
                         part.size
                             * ((current_time - part.deathtime) / (part.birthtime - part.deathtime))
@@ -740,21 +440,34 @@ async fn main() {
                         a: part.color.a,
                     },
                 );
+            });
 
-                part.position = (
-                    part.position.0 + part.velocity.0 * delta_time,
-                    part.position.1 + part.velocity.1 * delta_time,
+            // Scores floating off whatever earned them.
+            for popup in &game.popups {
+                let text = popup.points.to_string();
+                let font_scale = 0.00007 * (DISPLAY_TARGET_WIDTH as f32);
+                let size = measure_text(&text, None, 340, font_scale);
+                let screen_position = game.playfield.to_screen(popup.position);
+                draw_text_ex(
+                    &text,
+                    screen_position.0 - size.width / 2.0,
+                    screen_position.1,
+                    TextParams {
+                        font_size: 340,
+                        font_scale,
+                        rotation: 0.0,
+                        color: Color {
+                            a: popup.alpha(),
+                            ..WHITE
+                        },
+                        ..Default::default()
+                    },
                 );
-
-                part.velocity = (part.velocity.0 * part.drag, part.velocity.1 * part.drag);
-            });
-            particles
-                .particles_container
-                .retain(|&part| part.deathtime > current_time);
+            }
 
             // And UI to draw underneath it all.
             draw_text_ex(
-                &format!("High Score: {}", high_score),
+                &format!("High Score: {}", game.high_score),
                 -68.0,
                 -32.0,
                 TextParams {
@@ -766,7 +479,7 @@ async fn main() {
                 },
             );
             draw_text_ex(
-                &format!("Score: {}", score),
+                &format!("Score: {}", game.score),
                 -68.0,
                 -26.0,
                 TextParams {
@@ -777,10 +490,28 @@ async fn main() {
                     ..Default::default()
                 },
             );
+            // Flashing when a life gets handed out.
+            let lives_color = if (game.extra_life_flash * 8.0) as u32 % 2 == 1 {
+                YELLOW
+            } else {
+                GRAY
+            };
             draw_text_ex(
-                &format!("Lives: {}", lives),
+                &format!("Lives: {}", game.lives),
                 -68.0,
                 -20.0,
+                TextParams {
+                    font_size: 340,
+                    font_scale: 0.0001 * (DISPLAY_TARGET_WIDTH as f32),
+                    rotation: 0.0,
+                    color: lives_color,
+                    ..Default::default()
+                },
+            );
+            draw_text_ex(
+                &format!("Wave: {}", game.wave),
+                -68.0,
+                -14.0,
                 TextParams {
                     font_size: 340,
                     font_scale: 0.0001 * (DISPLAY_TARGET_WIDTH as f32),
@@ -789,9 +520,90 @@ async fn main() {
                     ..Default::default()
                 },
             );
-            if lives <= 0 {
+            // Whatever the ship's got going, and how long it's got left, up from the bottom.
+            for (_id, power_ups) in game
+                .world
+                .query::<&components::PowerUps>()
+                .with::<&components::Controllable>()
+                .iter()
+            {
+                for (line, (kind, secs)) in power_ups.0.iter().enumerate() {
+                    draw_text_ex(
+                        &format!("{} {:.0}", kind.name(), secs.ceil()),
+                        -68.0,
+                        34.0 - line as f32 * 6.0,
+                        TextParams {
+                            font_size: 340,
+                            font_scale: 0.0001 * (DISPLAY_TARGET_WIDTH as f32),
+                            rotation: 0.0,
+                            color: kind.color(),
+                            ..Default::default()
+                        },
+                    );
+                }
+            }
+            // How much shield's left in the tank.
+            for (_id, shield) in game
+                .world
+                .query::<&components::Shield>()
+                .with::<&components::Controllable>()
+                .iter()
+            {
                 draw_text_ex(
-                    "Press R to Start!",
+                    "Shield",
+                    -68.0,
+                    -2.0,
+                    TextParams {
+                        font_size: 340,
+                        font_scale: 0.0001 * (DISPLAY_TARGET_WIDTH as f32),
+                        rotation: 0.0,
+                        color: GRAY,
+                        ..Default::default()
+                    },
+                );
+                draw_rectangle_lines(-68.0, -0.5, 24.0, 1.0, 0.25, GRAY);
+                draw_rectangle(
+                    -68.0,
+                    -0.5,
+                    24.0 * shield.0,
+                    1.0,
+                    components::PowerUpKind::Shield.color(),
+                );
+            }
+            // The chain going, and a bar for how long's left to keep it up.
+            if game.combo.chain > 0 {
+                draw_text_ex(
+                    &format!("Combo: x{} ({})", game.combo.multiplier(), game.combo.chain),
+                    -68.0,
+                    -8.0,
+                    TextParams {
+                        font_size: 340,
+                        font_scale: 0.0001 * (DISPLAY_TARGET_WIDTH as f32),
+                        rotation: 0.0,
+                        color: YELLOW,
+                        ..Default::default()
+                    },
+                );
+                draw_rectangle(-68.0, -6.5, 24.0 * game.combo.remaining(), 1.0, YELLOW);
+            }
+            // Letting everyone know what's coming, while there's a breather.
+            if game.lives > 0 && game.wave_incoming() {
+                draw_text_ex(
+                    &format!("WAVE {}", game.wave + 1),
+                    -10.0,
+                    -12.0,
+                    TextParams {
+                        font_size: 340,
+                        font_scale: 0.0001 * (DISPLAY_TARGET_WIDTH as f32),
+                        rotation: 0.0,
+                        color: WHITE,
+                        ..Default::default()
+                    },
+                );
+            }
+            if game.lives == 0 {
+                draw_text_ex(
+                    &format!("Press {} to Start!", input_map.label(Action::Restart)),
                     -29.0,
                     0.0,
                     TextParams {
@@ -802,8 +614,63 @@ async fn main() {
                         ..Default::default()
                     },
                 );
+                // How the last run went, if there was one.
+                if game.stats.shots_fired > 0 {
+                    draw_text_ex(
+                        &format!(
+                            "Hits: {}/{} ({:.0}%)",
+                            game.stats.hits,
+                            game.stats.shots_fired,
+                            game.stats.accuracy()
+                        ),
+                        -29.0,
+                        18.0,
+                        TextParams {
+                            font_size: 340,
+                            font_scale: 0.0001 * (DISPLAY_TARGET_WIDTH as f32),
+                            rotation: 0.0,
+                            color: GRAY,
+                            ..Default::default()
+                        },
+                    );
+                    draw_text_ex(
+                        &format!(
+                            "Best Chain: {} (x{})",
+                            game.stats.best_chain, game.stats.best_multiplier
+                        ),
+                        -29.0,
+                        24.0,
+                        TextParams {
+                            font_size: 340,
+                            font_scale: 0.0001 * (DISPLAY_TARGET_WIDTH as f32),
+                            rotation: 0.0,
+                            color: GRAY,
+                            ..Default::default()
+                        },
+                    );
+                }
+                if playback.is_none() {
+                    draw_text_ex(
+                        &format!("Difficulty: < {} >", difficulties[selected_difficulty].name),
+                        -29.0,
+                        12.0,
+                        TextParams {
+                            font_size: 340,
+                            font_scale: 0.0001 * (DISPLAY_TARGET_WIDTH as f32),
+                            rotation: 0.0,
+                            color: GRAY,
+                            ..Default::default()
+                        },
+                    );
+                }
                 draw_text_ex(
-                    "Use WASD to move!",
+                    &format!(
+                        "Use {}{}{}{} to move!",
+                        input_map.label(Action::Thrust),
+                        input_map.label(Action::RotateLeft),
+                        input_map.label(Action::Brake),
+                        input_map.label(Action::RotateRight)
+                    ),
                     -29.0,
                     6.0,
                     TextParams {
@@ -815,20 +682,86 @@ async fn main() {
                     },
                 );
             }
+            // Letting whoever's watching know it's a replay, and where they are in it.
+            if let Some(playback) = &playback {
+                draw_text_ex(
+                    &format!(
+                        "Replay {}/{}{}",
+                        playback.cursor,
+                        playback.replay.inputs.len(),
+                        if playback.finished() {
+                            " (Done)"
+                        } else if playback.paused {
+                            " (Paused)"
+                        } else {
+                            ""
+                        }
+                    ),
+                    20.0,
+                    -32.0,
+                    TextParams {
+                        font_size: 340,
+                        font_scale: 0.0001 * (DISPLAY_TARGET_WIDTH as f32),
+                        rotation: 0.0,
+                        color: GRAY,
+                        ..Default::default()
+                    },
+                );
+                draw_text_ex(
+                    &format!("{} Pause, F Fast, N Step", input_map.label(Action::Pause)),
+                    20.0,
+                    -26.0,
+                    TextParams {
+                        font_size: 340,
+                        font_scale: 0.0001 * (DISPLAY_TARGET_WIDTH as f32),
+                        rotation: 0.0,
+                        color: GRAY,
+                        ..Default::default()
+                    },
+                );
+            }
 
+            if paused {
+                draw_text_ex(
+                    "Paused",
+                    -12.0,
+                    -12.0,
+                    TextParams {
+                        font_size: 340,
+                        font_scale: 0.0001 * (DISPLAY_TARGET_WIDTH as f32),
+                        rotation: 0.0,
+                        color: GRAY,
+                        ..Default::default()
+                    },
+                );
+            }
             // For debugging the game.
-            if crate::DEBUG_ENABLED {
-                world
-                    .query::<(&components::Position, &components::Collidable)>()
+            if DEBUG_ENABLED {
+                game.world
+                    .query::<(
+                        &components::Position,
+                        Option<&components::LastPosition>,
+                        &components::Collidable,
+                    )>()
+                    .without::<&components::Hyperspace>()
+                    .without::<&components::Respawning>()
                     .iter()
-                    .for_each(|collidable| {
+                    .for_each(|(_id, (position, last_position, collidable))| {
+                        let position = match last_position {
+                            Some(last_position) => interpolate_position(
+                                last_position,
+                                position,
+                                alpha,
+                                &game.playfield,
+                            ),
+                            None => *position,
+                        };
+                        let screen_position = game.playfield.to_screen((position.0, position.1));
                         draw_circle(
-                            (collidable.1 .0 .0 % crate::DISPLAY_TARGET_WIDTH as f32)
-                                - (crate::DISPLAY_TARGET_WIDTH as f32 / 2.0),
-                            (collidable.1 .0 .1 % crate::DISPLAY_TARGET_HEIGHT as f32)
-                                - (crate::DISPLAY_TARGET_HEIGHT as f32 / 2.0),
-                            collidable.1 .1 .0,
-                            match collidable.1 .1 .1 {
+                            screen_position.0,
+                            screen_position.1,
+                            collidable.0,
+                            match collidable.1 {
                                 components::CollidableType::ASTEROID => Color {
                                     r: 0.0,
                                     g: 0.0,
@@ -847,50 +780,158 @@ async fn main() {
                                     b: 0.0,
                                     a: 0.5,
                                 },
+                                components::CollidableType::SAUCER => Color {
+                                    r: 1.0,
+                                    g: 0.0,
+                                    b: 1.0,
+                                    a: 0.5,
+                                },
+                                components::CollidableType::PICKUP => Color {
+                                    r: 1.0,
+                                    g: 1.0,
+                                    b: 0.0,
+                                    a: 0.5,
+                                },
                             },
                         );
                     });
             }
 
             // All drawable, transformable objects.
-            world
-                .query::<(&components::Position, &components::Draw)>() // Querying the world.
+            game.world
+                .query::<(
+                    &components::Position,
+                    Option<&components::LastPosition>,
+                    &components::Draw,
+                    Option<&components::Invulnerable>,
+                    Option<&components::Shield>,
+                )>() // Querying the world.
+                .without::<&components::Hyperspace>() // Nothing to see of anything in hyperspace,
+                .without::<&components::Respawning>() // or waiting to respawn.
                 .iter() // Iterating over it.
-                .for_each(|drawable| {
-                    // For each drawable we find.
-                    let drawable_x = (drawable.1 .0 .0 % crate::DISPLAY_TARGET_WIDTH as f32)
-                        - (crate::DISPLAY_TARGET_WIDTH as f32 / 2.0);
-                    let drawable_y = (drawable.1 .0 .1 % crate::DISPLAY_TARGET_HEIGHT as f32)
-                        - (crate::DISPLAY_TARGET_HEIGHT as f32 / 2.0);
-                    if drawable.1 .1 .1.len() > 1 {
-                        for vector_index in 1..drawable.1 .1 .1.len() {
-                            let start_point = rotate_point(
-                                (
-                                    drawable.1 .1 .1[vector_index - 1].0,
-                                    drawable.1 .1 .1[vector_index - 1].1,
-                                ),
-                                drawable.1 .0 .2,
-                            );
-                            let end_point = rotate_point(
-                                (
-                                    drawable.1 .1 .1[vector_index].0,
-                                    drawable.1 .1 .1[vector_index].1,
-                                ),
-                                drawable.1 .0 .2,
-                            );
-                            draw_line(
-                                drawable_x + start_point.0,
-                                drawable_y + start_point.1,
-                                drawable_x + end_point.0,
-                                drawable_y + end_point.1,
-                                0.75,
-                                drawable.1 .1 .0,
-                            );
+                .for_each(
+                    |(_id, (position, last_position, draw, invulnerable, shield))| {
+                        // Blinking while invulnerable.
+                        if invulnerable.is_some_and(|invulnerable| !invulnerable.visible()) {
+                            return;
                         }
-                    } else {
-                        draw_circle(drawable_x, drawable_y, 1.0, WHITE);
-                    }
-                });
+                        // For each drawable we find, figuring out where it is between ticks.
+                        let position = match last_position {
+                            Some(last_position) => interpolate_position(
+                                last_position,
+                                position,
+                                alpha,
+                                &game.playfield,
+                            ),
+                            None => *position,
+                        };
+                        let screen_position = game.playfield.to_screen((position.0, position.1));
+                        // Anything hanging over an edge gets drawn again on the other side.
+                        for offset in game.playfield.ghost_offsets(
+                            (position.0, position.1),
+                            narrowphase::bounding_radius(&draw.1).max(1.0),
+                        ) {
+                            let drawable_x = screen_position.0 + offset.0;
+                            let drawable_y = screen_position.1 + offset.1;
+                            if draw.1.len() > 1 {
+                                for vector_index in 1..draw.1.len() {
+                                    let start_point = rotate_point(
+                                        (draw.1[vector_index - 1].0, draw.1[vector_index - 1].1),
+                                        position.2,
+                                    );
+                                    let end_point = rotate_point(
+                                        (draw.1[vector_index].0, draw.1[vector_index].1),
+                                        position.2,
+                                    );
+                                    draw_line(
+                                        drawable_x + start_point.0,
+                                        drawable_y + start_point.1,
+                                        drawable_x + end_point.0,
+                                        drawable_y + end_point.1,
+                                        0.75,
+                                        draw.0,
+                                    );
+                                }
+                            } else {
+                                draw_circle(drawable_x, drawable_y, 1.0, WHITE);
+                            }
+                            // A bubble around anything with its shield up.
+                            if shield.is_some_and(|shield| shield.1) {
+                                draw_circle_lines(
+                                    drawable_x,
+                                    drawable_y,
+                                    shield::SHIELD_RADIUS,
+                                    0.5,
+                                    components::PowerUpKind::Shield.color(),
+                                );
+                            }
+                        }
+                    },
+                );
+
+            // The controls menu goes over the top of everything.
+            if controls_menu.open {
+                draw_rectangle(
+                    -70.0,
+                    -38.0,
+                    140.0,
+                    76.0,
+                    Color {
+                        r: 0.0,
+                        g: 0.0,
+                        b: 0.0,
+                        a: 0.9,
+                    },
+                );
+                draw_text_ex(
+                    "Controls",
+                    -66.0,
+                    -30.0,
+                    TextParams {
+                        font_size: 340,
+                        font_scale: 0.0001 * (DISPLAY_TARGET_WIDTH as f32),
+                        rotation: 0.0,
+                        color: GRAY,
+                        ..Default::default()
+                    },
+                );
+                draw_text_ex(
+                    "Enter Bind, Bksp Clear, Tab Done",
+                    -66.0,
+                    36.0,
+                    TextParams {
+                        font_size: 340,
+                        font_scale: 0.0001 * (DISPLAY_TARGET_WIDTH as f32),
+                        rotation: 0.0,
+                        color: GRAY,
+                        ..Default::default()
+                    },
+                );
+                for (index, action) in Action::ALL.iter().enumerate() {
+                    let selected = index == controls_menu.selected;
+                    draw_text_ex(
+                        &format!(
+                            "{} {}: {}",
+                            if selected { ">" } else { " " },
+                            action.name(),
+                            if selected && controls_menu.listening {
+                                "Press a key, Esc cancels".to_string()
+                            } else {
+                                input_map.describe(*action)
+                            }
+                        ),
+                        -66.0,
+                        -24.0 + index as f32 * 6.0,
+                        TextParams {
+                            font_size: 340,
+                            font_scale: 0.0001 * (DISPLAY_TARGET_WIDTH as f32),
+                            rotation: 0.0,
+                            color: if selected { WHITE } else { GRAY },
+                            ..Default::default()
+                        },
+                    );
+                }
+            }
         }
 
         // DRAWING OUR TEXTURE TO THE SCREEN
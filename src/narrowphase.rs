@@ -0,0 +1,232 @@
+// Shape-on-shape checks, for when "close enough" circles aren't good enough.
+use crate::components;
+use crate::{rotate_point, square_distance};
+
+// Turning a Draw outline into world space, rotated and moved to where the entity is.
+pub fn transform_outline(
+    outline: &[(f32, f32)],
+    position: &components::Position,
+) -> Vec<(f32, f32)> {
+    outline
+        .iter()
+        .map(|&point| {
+            let rotated = rotate_point(point, position.2);
+            (rotated.0 + position.0, rotated.1 + position.1)
+        })
+        .collect()
+}
+
+// Sliding a world-space outline over, like when its owner is just across the seam.
+pub fn translate_outline(outline: &[(f32, f32)], offset: (f32, f32)) -> Vec<(f32, f32)> {
+    outline
+        .iter()
+        .map(|&(x, y)| (x + offset.0, y + offset.1))
+        .collect()
+}
+
+// How far the outline reaches from its centre.
+pub fn bounding_radius(outline: &[(f32, f32)]) -> f32 {
+    outline
+        .iter()
+        .map(|&(x, y)| (x * x + y * y).sqrt())
+        .fold(0.0, f32::max)
+}
+
+// Outlines that end where they start have an inside, everything else is just lines.
+fn is_closed(outline: &[(f32, f32)]) -> bool {
+    outline.len() >= 4 && outline.first() == outline.last()
+}
+
+fn edges(outline: &[(f32, f32)]) -> impl Iterator<Item = ((f32, f32), (f32, f32))> + '_ {
+    outline.windows(2).map(|pair| (pair[0], pair[1]))
+}
+
+// Even-odd test, so the dents in rocks count as outside.
+pub fn point_in_outline(point: (f32, f32), outline: &[(f32, f32)]) -> bool {
+    if !is_closed(outline) {
+        return false;
+    }
+    let mut inside = false;
+    for (start, end) in edges(outline) {
+        if (start.1 > point.1) != (end.1 > point.1)
+            && point.0 < (end.0 - start.0) * (point.1 - start.1) / (end.1 - start.1) + start.0
+        {
+            inside = !inside;
+        }
+    }
+    inside
+}
+
+pub fn segment_distance_squared(point: (f32, f32), start: (f32, f32), end: (f32, f32)) -> f32 {
+    let length_squared = square_distance(start.0, start.1, end.0, end.1);
+    if length_squared == 0.0 {
+        return square_distance(point.0, point.1, start.0, start.1);
+    }
+    let along = (((point.0 - start.0) * (end.0 - start.0)
+        + (point.1 - start.1) * (end.1 - start.1))
+        / length_squared)
+        .clamp(0.0, 1.0);
+    square_distance(
+        point.0,
+        point.1,
+        start.0 + (end.0 - start.0) * along,
+        start.1 + (end.1 - start.1) * along,
+    )
+}
+
+pub fn segments_intersect(
+    first: ((f32, f32), (f32, f32)),
+    second: ((f32, f32), (f32, f32)),
+) -> bool {
+    let cross = |o: (f32, f32), a: (f32, f32), b: (f32, f32)| {
+        (a.0 - o.0) * (b.1 - o.1) - (a.1 - o.1) * (b.0 - o.0)
+    };
+    let d1 = cross(second.0, second.1, first.0);
+    let d2 = cross(second.0, second.1, first.1);
+    let d3 = cross(first.0, first.1, second.0);
+    let d4 = cross(first.0, first.1, second.1);
+    ((d1 > 0.0) != (d2 > 0.0)) && ((d3 > 0.0) != (d4 > 0.0))
+}
+
+// How close two line segments get, which is nothing at all if they cross.
+pub fn segments_distance_squared(
+    first: ((f32, f32), (f32, f32)),
+    second: ((f32, f32), (f32, f32)),
+) -> f32 {
+    if segments_intersect(first, second) {
+        return 0.0;
+    }
+    segment_distance_squared(first.0, second.0, second.1)
+        .min(segment_distance_squared(first.1, second.0, second.1))
+        .min(segment_distance_squared(second.0, first.0, first.1))
+        .min(segment_distance_squared(second.1, first.0, first.1))
+}
+
+// A plain circle against an outline.
+pub fn circle_touches_outline(center: (f32, f32), radius: f32, outline: &[(f32, f32)]) -> bool {
+    point_in_outline(center, outline)
+        || edges(outline)
+            .any(|(start, end)| segment_distance_squared(center, start, end) < radius * radius)
+}
+
+// Two outlines touch if any of their lines cross, or one is sat entirely inside the other.
+pub fn outlines_touch(first: &[(f32, f32)], second: &[(f32, f32)]) -> bool {
+    edges(first).any(|first_edge| {
+        edges(second).any(|second_edge| segments_intersect(first_edge, second_edge))
+    }) || first
+        .first()
+        .is_some_and(|&point| point_in_outline(point, second))
+        || second
+            .first()
+            .is_some_and(|&point| point_in_outline(point, first))
+}
+
+// A circle dragged along a path against an outline, so nothing gets skipped over between ticks.
+pub fn swept_circle_touches_outline(
+    path: ((f32, f32), (f32, f32)),
+    radius: f32,
+    outline: &[(f32, f32)],
+) -> bool {
+    point_in_outline(path.0, outline)
+        || point_in_outline(path.1, outline)
+        || edges(outline).any(|edge| segments_distance_squared(path, edge) < radius * radius)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A 10x10 square with a notch cut into its right side, closed back up at the start.
+    const NOTCHED: [(f32, f32); 8] = [
+        (0.0, 0.0),
+        (10.0, 0.0),
+        (10.0, 4.0),
+        (5.0, 5.0),
+        (10.0, 6.0),
+        (10.0, 10.0),
+        (0.0, 10.0),
+        (0.0, 0.0),
+    ];
+
+    #[test]
+    fn points_inside_outlines() {
+        assert!(point_in_outline((2.0, 2.0), &NOTCHED));
+        assert!(!point_in_outline((8.0, 5.0), &NOTCHED)); // In the notch.
+        assert!(!point_in_outline((12.0, 2.0), &NOTCHED));
+        // Lines that don't close don't have an inside.
+        assert!(!point_in_outline((2.0, 2.0), &NOTCHED[..7]));
+    }
+
+    #[test]
+    fn segments_crossing() {
+        assert!(segments_intersect(
+            ((0.0, 0.0), (4.0, 4.0)),
+            ((0.0, 4.0), (4.0, 0.0))
+        ));
+        assert!(!segments_intersect(
+            ((0.0, 0.0), (4.0, 0.0)),
+            ((0.0, 1.0), (4.0, 1.0))
+        ));
+        assert_eq!(
+            segment_distance_squared((2.0, 3.0), (0.0, 0.0), (4.0, 0.0)),
+            9.0
+        );
+        assert_eq!(
+            segment_distance_squared((7.0, 4.0), (0.0, 0.0), (4.0, 0.0)),
+            25.0
+        );
+    }
+
+    #[test]
+    fn circles_and_outlines() {
+        assert!(circle_touches_outline((11.0, 2.0), 1.5, &NOTCHED));
+        assert!(!circle_touches_outline((12.0, 2.0), 1.5, &NOTCHED));
+        assert!(!circle_touches_outline((8.5, 5.0), 0.5, &NOTCHED));
+        assert!(circle_touches_outline((5.0, 8.0), 0.1, &NOTCHED)); // Sat well inside.
+    }
+
+    #[test]
+    fn outlines_touching() {
+        let small = translate_outline(
+            &[(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 0.0)],
+            (2.0, 2.0),
+        );
+        assert!(outlines_touch(&NOTCHED, &small)); // Entirely inside.
+        assert!(outlines_touch(&small, &NOTCHED));
+        // Stood on end, poking up through the bottom edge.
+        let poking = transform_outline(
+            &[(-2.0, 0.0), (2.0, 0.0)],
+            &components::Position(5.0, -1.0, 90.0),
+        );
+        assert!(outlines_touch(&NOTCHED, &poking));
+        assert!(!outlines_touch(
+            &NOTCHED,
+            &translate_outline(&poking, (0.0, -3.0))
+        ));
+        assert_eq!(bounding_radius(&[(3.0, 4.0), (-1.0, 0.0)]), 5.0);
+    }
+
+    #[test]
+    fn fast_circles_dont_skip_thin_outlines() {
+        let wall = [(0.0, -5.0), (0.0, 5.0)];
+        // Starting well to one side and ending well to the other, without ever landing on it.
+        assert!(!circle_touches_outline((-6.0, 0.0), 1.0, &wall));
+        assert!(!circle_touches_outline((6.0, 0.0), 1.0, &wall));
+        assert!(swept_circle_touches_outline(
+            ((-6.0, 0.0), (6.0, 0.0)),
+            1.0,
+            &wall
+        ));
+        assert!(!swept_circle_touches_outline(
+            ((-6.0, 7.0), (6.0, 7.0)),
+            1.0,
+            &wall
+        ));
+        // Ending up sat inside counts too.
+        assert!(swept_circle_touches_outline(
+            ((-20.0, 5.0), (5.0, 5.0)),
+            0.1,
+            &NOTCHED
+        ));
+    }
+}
@@ -0,0 +1,99 @@
+use macroquad::prelude::*;
+
+use crate::game::DRAG_REFERENCE_RATE;
+use crate::playfield::Playfield;
+use crate::rng::Rng;
+
+// Particle Sutff for obvi reasons.
+//
+#[derive(Default, Clone, Copy)]
+pub struct Particle {
+    pub position: (f32, f32),
+    pub velocity: (f32, f32),
+    pub drag: f32,
+    pub size: f32,
+    pub color: Color,
+    pub birthtime: f64,
+    pub deathtime: f64,
+}
+
+#[derive(Default, Clone)]
+pub struct ParticleStorage {
+    pub particles_container: Vec<Particle>,
+    pub current_time: f64, // Simulation time, so particles don't need a window to age.
+    pub rng: Rng,          // Particles get their own stream, so they never change the game.
+}
+
+impl ParticleStorage {
+    pub fn new(rng: Rng) -> Self {
+        Self {
+            particles_container: Vec::new(),
+            current_time: 0.0,
+            rng,
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn create_particle(
+        &mut self,
+        count: i32,
+        position: (f32, f32),
+        velocity: (f32, f32),
+        drag: f32,
+        size: f32,
+        color: Color,
+        age: f64,
+        position_variance: (f32, f32),
+        velocity_variance: (f32, f32),
+        size_variance: f32,
+        age_variance: f64,
+    ) {
+        let curr_time = self.current_time;
+        for _i in 0..count {
+            self.particles_container.push(Particle {
+                position: (
+                    position.0
+                        + self
+                            .rng
+                            .gen_range(-position_variance.0, position_variance.0),
+                    position.1
+                        + self
+                            .rng
+                            .gen_range(-position_variance.1, position_variance.1),
+                ),
+                velocity: (
+                    velocity.0
+                        + self
+                            .rng
+                            .gen_range(-velocity_variance.0, velocity_variance.0),
+                    velocity.1
+                        + self
+                            .rng
+                            .gen_range(-velocity_variance.1, velocity_variance.1),
+                ),
+                drag,
+                size: size + self.rng.gen_range(-size_variance, size_variance),
+                color,
+                birthtime: curr_time,
+                deathtime: curr_time + age + self.rng.gen_range_f64(-age_variance, age_variance),
+            })
+        }
+    }
+
+    // Moving every particle along and culling the dead ones.
+    pub fn update(&mut self, delta_time: f32, playfield: &Playfield) {
+        self.current_time += delta_time as f64;
+        self.particles_container.iter_mut().for_each(|part| {
+            part.position = playfield.wrap((
+                part.position.0 + part.velocity.0 * delta_time,
+                part.position.1 + part.velocity.1 * delta_time,
+            ));
+
+            let drag = part.drag.powf(delta_time * DRAG_REFERENCE_RATE);
+            part.velocity = (part.velocity.0 * drag, part.velocity.1 * drag);
+        });
+        let current_time = self.current_time;
+        self.particles_container
+            .retain(|&part| part.deathtime > current_time);
+    }
+}
@@ -0,0 +1,134 @@
+use crate::{DISPLAY_TARGET_HEIGHT, DISPLAY_TARGET_WIDTH};
+
+// How far the camera zooms in on the render target. The playfield is whatever that leaves visible.
+pub const CAMERA_ZOOM: (f32, f32) = (
+    0.0001 * DISPLAY_TARGET_HEIGHT as f32,
+    0.0001 * DISPLAY_TARGET_WIDTH as f32,
+);
+
+// The space everything lives in. Leave one side and you come back in on the other.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Playfield {
+    pub min: (f32, f32),
+    pub max: (f32, f32),
+}
+
+impl Default for Playfield {
+    // Exactly what the camera can see, centred where the display has always been centred.
+    fn default() -> Self {
+        let size = (2.0 / CAMERA_ZOOM.0, 2.0 / CAMERA_ZOOM.1);
+        Self::centered(
+            (
+                DISPLAY_TARGET_WIDTH as f32 / 2.0,
+                DISPLAY_TARGET_HEIGHT as f32 / 2.0,
+            ),
+            size,
+        )
+    }
+}
+
+impl Playfield {
+    pub fn new(min: (f32, f32), max: (f32, f32)) -> Self {
+        Self { min, max }
+    }
+
+    pub fn centered(center: (f32, f32), size: (f32, f32)) -> Self {
+        Self::new(
+            (center.0 - size.0 / 2.0, center.1 - size.1 / 2.0),
+            (center.0 + size.0 / 2.0, center.1 + size.1 / 2.0),
+        )
+    }
+
+    pub fn width(&self) -> f32 {
+        self.max.0 - self.min.0
+    }
+
+    pub fn height(&self) -> f32 {
+        self.max.1 - self.min.1
+    }
+
+    pub fn center(&self) -> (f32, f32) {
+        (
+            (self.min.0 + self.max.0) / 2.0,
+            (self.min.1 + self.max.1) / 2.0,
+        )
+    }
+
+    // Bringing a point back inside, coming in the opposite side it left from.
+    pub fn wrap(&self, point: (f32, f32)) -> (f32, f32) {
+        (
+            (point.0 - self.min.0).rem_euclid(self.width()) + self.min.0,
+            (point.1 - self.min.1).rem_euclid(self.height()) + self.min.1,
+        )
+    }
+
+    // The shortest way from one point to another, which might be across the seam.
+    pub fn delta(&self, from: (f32, f32), to: (f32, f32)) -> (f32, f32) {
+        let shortest = |difference: f32, size: f32| difference - size * (difference / size).round();
+        (
+            shortest(to.0 - from.0, self.width()),
+            shortest(to.1 - from.1, self.height()),
+        )
+    }
+
+    pub fn distance_squared(&self, from: (f32, f32), to: (f32, f32)) -> f32 {
+        let delta = self.delta(from, to);
+        delta.0 * delta.0 + delta.1 * delta.1
+    }
+
+    // Where a point ends up relative to the camera, which sits on the centre.
+    pub fn to_screen(&self, point: (f32, f32)) -> (f32, f32) {
+        let center = self.center();
+        (point.0 - center.0, point.1 - center.1)
+    }
+
+    // Something hanging over an edge needs drawing on the other side too.
+    // The first offset is always (0, 0), for the thing itself.
+    pub fn ghost_offsets(&self, point: (f32, f32), radius: f32) -> Vec<(f32, f32)> {
+        let mut horizontal = vec![0.0];
+        if point.0 - radius < self.min.0 {
+            horizontal.push(self.width());
+        }
+        if point.0 + radius > self.max.0 {
+            horizontal.push(-self.width());
+        }
+        let mut vertical = vec![0.0];
+        if point.1 - radius < self.min.1 {
+            vertical.push(self.height());
+        }
+        if point.1 + radius > self.max.1 {
+            vertical.push(-self.height());
+        }
+        vertical
+            .iter()
+            .flat_map(|&y| horizontal.iter().map(move |&x| (x, y)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIELD: Playfield = Playfield {
+        min: (0.0, 0.0),
+        max: (100.0, 50.0),
+    };
+
+    #[test]
+    fn wrap_comes_back_in_the_other_side() {
+        assert_eq!(FIELD.wrap((10.0, 20.0)), (10.0, 20.0));
+        assert_eq!(FIELD.wrap((105.0, -5.0)), (5.0, 45.0));
+        assert_eq!(FIELD.wrap((-250.0, 120.0)), (50.0, 20.0));
+        assert_eq!(FIELD.wrap((100.0, 50.0)), (0.0, 0.0));
+    }
+
+    #[test]
+    fn delta_takes_the_short_way() {
+        assert_eq!(FIELD.delta((10.0, 10.0), (30.0, 5.0)), (20.0, -5.0));
+        // Across the seam is shorter.
+        assert_eq!(FIELD.delta((95.0, 2.0), (5.0, 48.0)), (10.0, -4.0));
+        assert_eq!(FIELD.delta((5.0, 48.0), (95.0, 2.0)), (-10.0, 4.0));
+        assert_eq!(FIELD.distance_squared((95.0, 2.0), (5.0, 48.0)), 116.0);
+    }
+}
@@ -0,0 +1,42 @@
+use crate::game::Game;
+
+pub const POPUP_LIFETIME: f32 = 0.8; // Seconds a score hangs around after the kill.
+const POPUP_RISE_SPEED: f32 = 6.0; // How fast it floats up while it's there.
+
+// Points scored, shown where whatever was worth them got shot.
+#[derive(Debug, Clone, Copy)]
+pub struct ScorePopup {
+    pub position: (f32, f32),
+    pub points: u32,
+    pub age: f32, // Seconds since it showed up.
+}
+
+impl ScorePopup {
+    // How see-through it's got, from 1 fresh to 0 just about gone.
+    pub fn alpha(&self) -> f32 {
+        (1.0 - self.age / POPUP_LIFETIME).clamp(0.0, 1.0)
+    }
+}
+
+impl Game {
+    pub fn score_popup(&mut self, position: (f32, f32), points: u32) {
+        self.popups.push(ScorePopup {
+            position,
+            points,
+            age: 0.0,
+        });
+    }
+
+    // Floating them up and off, then dropping them.
+    pub fn popup_system(&mut self, delta_time: f32) {
+        let playfield = self.playfield;
+        for popup in &mut self.popups {
+            popup.age += delta_time;
+            popup.position = playfield.wrap((
+                popup.position.0,
+                popup.position.1 - POPUP_RISE_SPEED * delta_time,
+            ));
+        }
+        self.popups.retain(|popup| popup.age < POPUP_LIFETIME);
+    }
+}
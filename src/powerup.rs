@@ -0,0 +1,261 @@
+use hecs::*;
+use macroquad::prelude::*;
+
+use crate::collision::EXTRA_LIFE_FLASH;
+use crate::components::{self, CollidableType, PowerUpKind};
+use crate::game::{Game, Sound};
+use crate::rng::Rng;
+
+const PICKUP_CHANCE: f64 = 0.08; // How often a rock the player shoots leaves something behind.
+const PICKUP_LIFETIME: f32 = 10.0; // Seconds it floats about before it's gone.
+const PICKUP_RADIUS: f32 = 2.5;
+const PICKUP_DRIFT: f32 = 4.0; // How fast they wander off, at most.
+pub const SPREAD_ANGLE: f32 = 15.0; // Degrees between each bullet in a spread shot.
+pub const SLOW_TIME_SCALE: f32 = 0.5; // How fast everything else goes while time's slowed.
+
+impl PowerUpKind {
+    pub const ALL: [PowerUpKind; 6] = [
+        PowerUpKind::SpreadShot,
+        PowerUpKind::RapidFire,
+        PowerUpKind::Shield,
+        PowerUpKind::Bomb,
+        PowerUpKind::ExtraLife,
+        PowerUpKind::SlowTime,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            PowerUpKind::SpreadShot => "Spread Shot",
+            PowerUpKind::RapidFire => "Rapid Fire",
+            PowerUpKind::Shield => "Shield",
+            PowerUpKind::Bomb => "Bomb",
+            PowerUpKind::ExtraLife => "Extra Life",
+            PowerUpKind::SlowTime => "Slow Time",
+        }
+    }
+
+    // Seconds it lasts once picked up. The ones that happen right away don't last at all.
+    pub fn duration(&self) -> f32 {
+        match self {
+            PowerUpKind::SpreadShot => 10.0,
+            PowerUpKind::RapidFire => 10.0,
+            PowerUpKind::Shield => 6.0,
+            PowerUpKind::SlowTime => 6.0,
+            PowerUpKind::Bomb | PowerUpKind::ExtraLife => 0.0,
+        }
+    }
+
+    pub fn color(&self) -> Color {
+        match self {
+            PowerUpKind::SpreadShot => Color {
+                r: 1.0,
+                g: 0.6,
+                b: 0.1,
+                a: 1.0,
+            },
+            PowerUpKind::RapidFire => Color {
+                r: 1.0,
+                g: 0.2,
+                b: 0.2,
+                a: 1.0,
+            },
+            PowerUpKind::Shield => Color {
+                r: 0.3,
+                g: 0.8,
+                b: 1.0,
+                a: 1.0,
+            },
+            PowerUpKind::Bomb => Color {
+                r: 1.0,
+                g: 0.9,
+                b: 0.2,
+                a: 1.0,
+            },
+            PowerUpKind::ExtraLife => Color {
+                r: 1.0,
+                g: 1.0,
+                b: 1.0,
+                a: 1.0,
+            },
+            PowerUpKind::SlowTime => Color {
+                r: 0.7,
+                g: 0.4,
+                b: 1.0,
+                a: 1.0,
+            },
+        }
+    }
+
+    // Each one gets its own little picture, so you can tell what it is before grabbing it.
+    fn outline(&self) -> Vec<(f32, f32)> {
+        match self {
+            // Three lines fanning out.
+            PowerUpKind::SpreadShot => vec![
+                (-2.0, -2.0),
+                (0.0, 2.0),
+                (0.0, -2.5),
+                (0.0, 2.0),
+                (2.0, -2.0),
+            ],
+            // A lightning bolt.
+            PowerUpKind::RapidFire => vec![(1.0, -2.5), (-1.0, 0.0), (1.0, 0.0), (-1.0, 2.5)],
+            // A hexagon.
+            PowerUpKind::Shield => (0..=6)
+                .map(|side| {
+                    let angle = side as f32 * std::f32::consts::PI / 3.0;
+                    (angle.cos() * 2.0, angle.sin() * 2.0)
+                })
+                .collect(),
+            // A box with a fuse.
+            PowerUpKind::Bomb => vec![
+                (-1.5, -1.0),
+                (1.5, -1.0),
+                (1.5, 2.0),
+                (-1.5, 2.0),
+                (-1.5, -1.0),
+                (0.0, -1.0),
+                (1.0, -2.5),
+            ],
+            // A little ship.
+            PowerUpKind::ExtraLife => vec![
+                (-1.2, 1.8),
+                (0.0, -1.8),
+                (1.2, 1.8),
+                (0.0, 0.6),
+                (-1.2, 1.8),
+            ],
+            // An hourglass.
+            PowerUpKind::SlowTime => vec![
+                (-1.5, -2.0),
+                (1.5, -2.0),
+                (-1.5, 2.0),
+                (1.5, 2.0),
+                (-1.5, -2.0),
+            ],
+        }
+    }
+}
+
+impl components::PowerUps {
+    pub fn has(&self, kind: PowerUpKind) -> bool {
+        self.0.iter().any(|(active, _)| *active == kind)
+    }
+}
+
+// Something left behind for the player to grab, drifting off slowly.
+pub fn create_pickup(world: &mut World, rng: &mut Rng, point: (f32, f32), kind: PowerUpKind) {
+    world.spawn((
+        components::Position(point.0, point.1, 0.0),
+        components::Velocity(
+            rng.gen_range(-PICKUP_DRIFT, PICKUP_DRIFT),
+            rng.gen_range(-PICKUP_DRIFT, PICKUP_DRIFT),
+            90.0,
+        ),
+        components::Draw(kind.color(), kind.outline()),
+        components::Collidable(PICKUP_RADIUS, CollidableType::PICKUP),
+        components::CollisionLayers::default_for(CollidableType::PICKUP),
+        components::Damages::default_for(CollidableType::PICKUP),
+        components::Lifetime(PICKUP_LIFETIME, f32::MAX),
+        components::Pickup(kind),
+    ));
+}
+
+impl Game {
+    // Every so often, a rock the player shot leaves something behind.
+    pub fn drop_system(&mut self) {
+        for (this, this_kind, other, other_kind) in self.collision_sides() {
+            if this_kind != CollidableType::ASTEROID
+                || other_kind != CollidableType::BULLET
+                || !self.fired_by_player(other)
+                || !self.entities_to_destroy.contains(&this)
+                || self.rng.unit() >= PICKUP_CHANCE
+            {
+                continue;
+            }
+            let Some(position) = self.position_of(this) else {
+                continue;
+            };
+            let kind = PowerUpKind::ALL[self.rng.rand() as usize % PowerUpKind::ALL.len()];
+            create_pickup(
+                &mut self.world,
+                &mut self.rng,
+                (position.0, position.1),
+                kind,
+            );
+        }
+    }
+
+    // The player flying into something they can use.
+    pub fn pickup_system(&mut self) {
+        for (this, this_kind, other, other_kind) in self.collision_sides() {
+            if this_kind != CollidableType::PLAYER || other_kind != CollidableType::PICKUP {
+                continue;
+            }
+            let kind = match self.world.get::<&components::Pickup>(other) {
+                Ok(pickup) => pickup.0,
+                Err(_) => continue,
+            };
+            // Already grabbed this tick.
+            if self.entities_to_destroy.contains(&other) {
+                continue;
+            }
+            self.entities_to_destroy.push(other);
+            self.sounds.push(Sound::PowerUp);
+            match kind {
+                PowerUpKind::Bomb => {
+                    self.clear_screen = true;
+                    self.bomb = true;
+                }
+                PowerUpKind::ExtraLife => {
+                    if self.lives < self.difficulty.max_lives {
+                        self.lives += 1;
+                        self.extra_life_flash = EXTRA_LIFE_FLASH;
+                        self.sounds.push(Sound::ExtraLife);
+                    }
+                }
+                _ => self.grant_power_up(this, kind),
+            }
+        }
+    }
+
+    // Starting a timed one, or topping it back up if it's already going.
+    fn grant_power_up(&mut self, ship: Entity, kind: PowerUpKind) {
+        if let Ok(mut power_ups) = self.world.get::<&mut components::PowerUps>(ship) {
+            match power_ups.0.iter_mut().find(|(active, _)| *active == kind) {
+                Some(active) => active.1 = kind.duration(),
+                None => power_ups.0.push((kind, kind.duration())),
+            }
+            return;
+        }
+        let _ = self
+            .world
+            .insert_one(ship, components::PowerUps(vec![(kind, kind.duration())]));
+    }
+
+    // Running down whatever's active.
+    pub fn power_up_system(&mut self, delta_time: f32) {
+        for (_id, power_ups) in self.world.query_mut::<&mut components::PowerUps>() {
+            for active in power_ups.0.iter_mut() {
+                active.1 -= delta_time;
+            }
+            power_ups.0.retain(|(_, secs)| *secs > 0.0);
+        }
+    }
+
+    // Whether anyone's got something going.
+    pub fn power_up_active(&self, kind: PowerUpKind) -> bool {
+        self.world
+            .query::<&components::PowerUps>()
+            .iter()
+            .any(|(_id, power_ups)| power_ups.has(kind))
+    }
+
+    // How fast everything the player doesn't own is going, with slow time on or off.
+    pub fn time_scale(&self) -> f32 {
+        if self.power_up_active(PowerUpKind::SlowTime) {
+            SLOW_TIME_SCALE
+        } else {
+            1.0
+        }
+    }
+}
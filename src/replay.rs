@@ -0,0 +1,304 @@
+use std::io::{Error, ErrorKind, Read, Write};
+use std::path::Path;
+
+use crate::difficulty::Difficulty;
+use crate::game::{Inputs, SIMULATION_VERSION};
+
+pub const GAME_VERSION: &str = env!("CARGO_PKG_VERSION"); // Just for the record, it's the simulation version that decides whether a replay plays.
+const REPLAY_MAGIC: &[u8; 4] = b"AREP";
+const REPLAY_FORMAT: u8 = 4;
+
+pub const FAST_FORWARD_SPEED: u32 = 4; // How many ticks we run per tick while fast-forwarding.
+const TICK_RATES: std::ops::RangeInclusive<f32> = 30.0..=1000.0; // Anything outside this is a broken file, not a fast PC.
+
+impl Inputs {
+    // Packing a tick's worth of inputs into four bytes: thrust, rotate, brake, then fire, hyperspace and shield as bits.
+    pub fn to_bytes(&self) -> [u8; 4] {
+        let inputs = self.quantized();
+        [
+            (inputs.thrust * 255.0).round() as u8,
+            ((inputs.rotate * 127.0).round() as i8) as u8,
+            (inputs.brake * 255.0).round() as u8,
+            inputs.fire as u8 | (inputs.hyperspace as u8) << 1 | (inputs.shield as u8) << 2,
+        ]
+    }
+
+    pub fn from_bytes(bytes: [u8; 4]) -> Self {
+        Self {
+            thrust: bytes[0] as f32 / 255.0,
+            rotate: (bytes[1] as i8) as f32 / 127.0,
+            brake: bytes[2] as f32 / 255.0,
+            fire: bytes[3] & 1 != 0,
+            hyperspace: bytes[3] & 2 != 0,
+            shield: bytes[3] & 4 != 0,
+        }
+    }
+}
+
+// Everything needed to play a run back: the seed, the tick rate, the difficulty, and what was pressed every tick.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Replay {
+    pub simulation: u32, // Which rules it was played under.
+    pub version: String,
+    pub seed: u64,
+    pub tick_rate: f32,
+    pub difficulty: Difficulty,
+    pub inputs: Vec<Inputs>,
+}
+
+impl Replay {
+    pub fn new(seed: u64, tick_rate: f32, difficulty: Difficulty) -> Self {
+        Self {
+            simulation: SIMULATION_VERSION,
+            version: GAME_VERSION.to_string(),
+            seed,
+            tick_rate,
+            difficulty,
+            inputs: Vec::new(),
+        }
+    }
+
+    // Remembering what was pressed for one more tick.
+    pub fn record(&mut self, inputs: &Inputs) {
+        self.inputs.push(*inputs);
+    }
+
+    // Writing it out. Inputs are run-length encoded, since most ticks look like the one before.
+    pub fn write_to(&self, writer: &mut impl Write) -> std::io::Result<()> {
+        writer.write_all(REPLAY_MAGIC)?;
+        writer.write_all(&[REPLAY_FORMAT])?;
+        writer.write_all(&self.simulation.to_le_bytes())?;
+        writer.write_all(&[self.version.len() as u8])?;
+        writer.write_all(self.version.as_bytes())?;
+        writer.write_all(&self.seed.to_le_bytes())?;
+        writer.write_all(&self.tick_rate.to_le_bytes())?;
+        let difficulty = self.difficulty.to_config_string();
+        writer.write_all(&(difficulty.len() as u16).to_le_bytes())?;
+        writer.write_all(difficulty.as_bytes())?;
+        writer.write_all(&(self.inputs.len() as u32).to_le_bytes())?;
+
+        let mut index = 0;
+        while index < self.inputs.len() {
+            let bytes = self.inputs[index].to_bytes();
+            let mut run: u16 = 1;
+            while index + (run as usize) < self.inputs.len()
+                && run < u16::MAX
+                && self.inputs[index + run as usize].to_bytes() == bytes
+            {
+                run += 1;
+            }
+            writer.write_all(&bytes)?;
+            writer.write_all(&run.to_le_bytes())?;
+            index += run as usize;
+        }
+        Ok(())
+    }
+
+    pub fn read_from(reader: &mut impl Read) -> std::io::Result<Self> {
+        let mut magic = [0u8; 4];
+        reader.read_exact(&mut magic)?;
+        if &magic != REPLAY_MAGIC {
+            return Err(Error::new(ErrorKind::InvalidData, "not a replay file"));
+        }
+        let mut format = [0u8; 1];
+        reader.read_exact(&mut format)?;
+        if format[0] != REPLAY_FORMAT {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("unsupported replay format {}", format[0]),
+            ));
+        }
+        let mut simulation = [0u8; 4];
+        reader.read_exact(&mut simulation)?;
+        let simulation = u32::from_le_bytes(simulation);
+        let mut length = [0u8; 1];
+        reader.read_exact(&mut length)?;
+        let mut version = vec![0u8; length[0] as usize];
+        reader.read_exact(&mut version)?;
+        let version = String::from_utf8(version)
+            .map_err(|_| Error::new(ErrorKind::InvalidData, "replay version isn't utf-8"))?;
+        // Anything recorded under different rules would just go off the rails partway through.
+        if simulation != SIMULATION_VERSION {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!(
+                    "replay was recorded on version {} (simulation {}), this is simulation {}",
+                    version, simulation, SIMULATION_VERSION
+                ),
+            ));
+        }
+
+        let mut seed = [0u8; 8];
+        reader.read_exact(&mut seed)?;
+        let mut tick_rate = [0u8; 4];
+        reader.read_exact(&mut tick_rate)?;
+        let tick_rate = f32::from_le_bytes(tick_rate);
+        if !TICK_RATES.contains(&tick_rate) {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("replay tick rate {} doesn't make sense", tick_rate),
+            ));
+        }
+        let mut length = [0u8; 2];
+        reader.read_exact(&mut length)?;
+        let mut difficulty = vec![0u8; u16::from_le_bytes(length) as usize];
+        reader.read_exact(&mut difficulty)?;
+        let difficulty = String::from_utf8(difficulty)
+            .map_err(|_| Error::new(ErrorKind::InvalidData, "replay difficulty isn't utf-8"))?;
+        let difficulty = Difficulty::parse(&difficulty)?;
+        let mut tick_count = [0u8; 4];
+        reader.read_exact(&mut tick_count)?;
+        let tick_count = u32::from_le_bytes(tick_count) as usize;
+
+        // The count's straight from the file, so it's only trusted as far as the runs actually back it up.
+        let truncated = |error: Error| match error.kind() {
+            ErrorKind::UnexpectedEof => {
+                Error::new(ErrorKind::InvalidData, "replay ends before its inputs do")
+            }
+            _ => error,
+        };
+        let mut inputs = Vec::new();
+        while inputs.len() < tick_count {
+            let mut run = [0u8; 6];
+            reader.read_exact(&mut run).map_err(truncated)?;
+            let tick_inputs = Inputs::from_bytes([run[0], run[1], run[2], run[3]]);
+            let count = u16::from_le_bytes([run[4], run[5]]) as usize;
+            if count == 0 || inputs.len() + count > tick_count {
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    "replay inputs are corrupt",
+                ));
+            }
+            inputs.extend(std::iter::repeat_n(tick_inputs, count));
+        }
+
+        Ok(Self {
+            simulation,
+            version,
+            seed: u64::from_le_bytes(seed),
+            tick_rate,
+            difficulty,
+            inputs,
+        })
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> std::io::Result<()> {
+        let mut file = std::io::BufWriter::new(std::fs::File::create(path)?);
+        self.write_to(&mut file)?;
+        file.flush()
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        Self::read_from(&mut std::io::BufReader::new(std::fs::File::open(path)?))
+    }
+}
+
+// Walking through a replay tick by tick, with the usual VCR buttons.
+#[derive(Debug, Clone)]
+pub struct Playback {
+    pub replay: Replay,
+    pub cursor: usize, // Which tick we're on.
+    pub paused: bool,
+}
+
+impl Playback {
+    pub fn new(replay: Replay) -> Self {
+        Self {
+            replay,
+            cursor: 0,
+            paused: false,
+        }
+    }
+
+    // The inputs for the next tick, or None once we've run out.
+    pub fn next_inputs(&mut self) -> Option<Inputs> {
+        let inputs = self.replay.inputs.get(self.cursor).copied();
+        if inputs.is_some() {
+            self.cursor += 1;
+        }
+        inputs
+    }
+
+    pub fn finished(&self) -> bool {
+        self.cursor >= self.replay.inputs.len()
+    }
+
+    // Back to the start, for watching it again.
+    pub fn rewind(&mut self) {
+        self.cursor = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> Replay {
+        let mut replay = Replay::new(0xDEAD_BEEF, 120.0, Difficulty::hard());
+        let held = Inputs {
+            thrust: 1.0,
+            rotate: -1.0,
+            fire: true,
+            ..Default::default()
+        };
+        for tick in 0..300 {
+            let inputs = if tick % 7 == 0 {
+                Inputs::default()
+            } else {
+                held
+            };
+            replay.record(&inputs);
+        }
+        replay.record(&Inputs {
+            brake: 1.0,
+            hyperspace: true,
+            shield: true,
+            ..Default::default()
+        });
+        replay
+    }
+
+    fn bytes_of(replay: &Replay) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        replay.write_to(&mut bytes).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn round_trips() {
+        let replay = sample();
+        let read = Replay::read_from(&mut bytes_of(&replay).as_slice()).unwrap();
+        assert_eq!(read, replay);
+    }
+
+    #[test]
+    fn huge_tick_count_is_invalid_not_fatal() {
+        let mut bytes = bytes_of(&Replay::new(1, 120.0, Difficulty::normal()));
+        let count_at = bytes.len() - 4;
+        bytes[count_at..].copy_from_slice(&u32::MAX.to_le_bytes());
+        let error = Replay::read_from(&mut bytes.as_slice()).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn bad_headers_are_invalid() {
+        let mut bytes = bytes_of(&sample());
+        bytes[0] = b'X';
+        let error = Replay::read_from(&mut bytes.as_slice()).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+
+        for tick_rate in [f32::NAN, 0.0, -120.0, 1.0, 1e30, f32::INFINITY] {
+            let replay = Replay::new(1, tick_rate, Difficulty::normal());
+            let error = Replay::read_from(&mut bytes_of(&replay).as_slice()).unwrap_err();
+            assert_eq!(error.kind(), ErrorKind::InvalidData, "{}", tick_rate);
+        }
+    }
+
+    #[test]
+    fn other_simulations_are_rejected() {
+        let mut replay = sample();
+        replay.simulation += 1;
+        let error = Replay::read_from(&mut bytes_of(&replay).as_slice()).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+    }
+}
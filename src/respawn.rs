@@ -0,0 +1,125 @@
+use crate::components;
+use crate::game::{Game, Sound};
+
+pub const RESPAWN_DELAY: f32 = 1.5; // Seconds after dying before the ship tries to come back.
+const RESPAWN_SAFE_DISTANCE: f32 = 20.0; // How much room the middle needs, on top of whatever's nearby's size.
+const INVULNERABILITY: f32 = 3.0; // Seconds of not being hit after coming back.
+const BLINK_RATE: f32 = 8.0; // Blinks a second while invulnerable.
+
+impl components::Invulnerable {
+    // Whether it's on the visible half of a blink.
+    pub fn visible(&self) -> bool {
+        (self.0 * BLINK_RATE) as i32 % 2 == 0
+    }
+}
+
+impl Game {
+    // Bringing dead ships back in the middle once it's clear, and wearing off invulnerability.
+    pub fn respawn_system(&mut self, delta_time: f32) {
+        let mut expired = Vec::new();
+        for (id, invulnerable) in self.world.query_mut::<&mut components::Invulnerable>() {
+            invulnerable.0 -= delta_time;
+            if invulnerable.0 <= 0.0 {
+                expired.push(id);
+            }
+        }
+        for id in expired {
+            let _ = self.world.remove_one::<components::Invulnerable>(id);
+        }
+
+        let mut ready = Vec::new();
+        for (id, respawning) in self.world.query_mut::<&mut components::Respawning>() {
+            respawning.0 -= delta_time;
+            if respawning.0 <= 0.0 {
+                ready.push(id);
+            }
+        }
+        if ready.is_empty() {
+            return;
+        }
+
+        // Waiting on anything that could hurt us to get out of the way. Our own stray bullets don't count.
+        let center = self.playfield.center();
+        let playfield = self.playfield;
+        let safe = self
+            .world
+            .query::<(
+                &components::Position,
+                &components::Collidable,
+                Option<&components::Owner>,
+            )>()
+            .iter()
+            .filter(|(id, (_, _, owner))| {
+                self.damages_of(*id) & components::LAYER_PLAYER != 0
+                    && !owner.is_some_and(|owner| ready.contains(&owner.0))
+            })
+            .all(|(_id, (position, collidable, _))| {
+                let distance = RESPAWN_SAFE_DISTANCE + collidable.0;
+                playfield.distance_squared(center, (position.0, position.1)) > distance * distance
+            });
+        if !safe {
+            return;
+        }
+
+        for id in ready {
+            let _ = self.world.remove_one::<components::Respawning>(id);
+            if let Ok((position, velocity)) = self
+                .world
+                .query_one_mut::<(&mut components::Position, &mut components::Velocity)>(id)
+            {
+                *position = components::Position(center.0, center.1, 0.0);
+                *velocity = components::Velocity(0.0, 0.0, 0.0);
+            }
+            let _ = self
+                .world
+                .insert_one(id, components::Invulnerable(INVULNERABILITY));
+            self.sounds.push(Sound::PlayerSpawn);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::create_asteroid_point;
+    use crate::powerup::create_pickup;
+
+    fn waiting_ship(game: &mut Game) -> hecs::Entity {
+        game.restart(1);
+        let (ship, _) = game
+            .world
+            .query_mut::<&components::Controllable>()
+            .into_iter()
+            .next()
+            .unwrap();
+        game.world
+            .insert_one(ship, components::Respawning(0.0))
+            .unwrap();
+        ship
+    }
+
+    #[test]
+    fn pickups_in_the_middle_dont_hold_up_respawning() {
+        let mut game = Game::new(1);
+        let ship = waiting_ship(&mut game);
+        let center = game.playfield.center();
+        create_pickup(
+            &mut game.world,
+            &mut game.rng,
+            center,
+            components::PowerUpKind::Bomb,
+        );
+        game.respawn_system(0.1);
+        assert!(game.world.get::<&components::Respawning>(ship).is_err());
+    }
+
+    #[test]
+    fn rocks_in_the_middle_do() {
+        let mut game = Game::new(1);
+        let ship = waiting_ship(&mut game);
+        let center = game.playfield.center();
+        create_asteroid_point(&mut game.world, &mut game.rng, center, 8.0, 0.0);
+        game.respawn_system(0.1);
+        assert!(game.world.get::<&components::Respawning>(ship).is_ok());
+    }
+}
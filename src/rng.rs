@@ -0,0 +1,54 @@
+// Our own little random number generator, so a seed always gives the same game.
+// It's SplitMix64, which is tiny, fast and plenty random for rocks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Default for Rng {
+    fn default() -> Self {
+        Self::new(0)
+    }
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    // Making a second, unrelated stream out of the same seed.
+    pub fn stream(seed: u64, stream: u64) -> Self {
+        Self::new(seed ^ stream.wrapping_mul(0x9E37_79B9_7F4A_7C15))
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    // Same idea as macroquad's rand::rand().
+    pub fn rand(&mut self) -> u32 {
+        (self.next_u64() >> 32) as u32
+    }
+
+    // Somewhere in 0..1.
+    pub fn unit(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    pub fn gen_range(&mut self, low: f32, high: f32) -> f32 {
+        low + (high - low) * self.unit() as f32
+    }
+
+    pub fn gen_range_f64(&mut self, low: f64, high: f64) -> f64 {
+        low + (high - low) * self.unit()
+    }
+}
+
+// Which stream is which, so nothing cosmetic can knock gameplay off course.
+pub const STREAM_GAMEPLAY: u64 = 0;
+pub const STREAM_COSMETIC: u64 = 1;
+pub const STREAM_AUDIO: u64 = 2;
@@ -0,0 +1,313 @@
+use hecs::*;
+use macroquad::prelude::*;
+
+use crate::components::{self, SaucerSize};
+use crate::game::{create_bullet, Game, Sound};
+use crate::rng::Rng;
+use crate::{deg2rad, rad2deg};
+
+pub const FIRST_SAUCER_DELAY: f32 = 15.0; // Seconds into a run before the first one shows up.
+const SAUCER_BULLET_SPEED: f32 = 25.0;
+const SMALL_SAUCER_SCORE: f32 = 2000.0; // Below this, it's only ever the big one.
+const SMALL_SAUCER_MAX_CHANCE: f32 = 0.8;
+const SMALL_SAUCER_SPREAD: (f32, f32) = (30.0, 3.0); // How far off it aims in degrees, from a fresh run down to its best.
+const SMALL_SAUCER_SPREAD_PER_POINT: f32 = 0.0025; // How much better it gets for every point scored.
+
+impl SaucerSize {
+    pub fn radius(&self) -> f32 {
+        match self {
+            SaucerSize::Large => 3.5,
+            SaucerSize::Small => 2.0,
+        }
+    }
+
+    fn speed(&self) -> f32 {
+        match self {
+            SaucerSize::Large => 12.0,
+            SaucerSize::Small => 18.0,
+        }
+    }
+
+    // Seconds between shots.
+    fn fire_interval(&self) -> f32 {
+        match self {
+            SaucerSize::Large => 1.5,
+            SaucerSize::Small => 1.0,
+        }
+    }
+}
+
+// A saucer coming in from the left or right edge, headed across to the other side.
+pub fn create_saucer(
+    world: &mut World,
+    rng: &mut Rng,
+    distance: f32,
+    point: (f32, f32),
+    size: SaucerSize,
+) {
+    let direction = if rng.unit() < 0.5 { 1.0 } else { -1.0 };
+    let scale = size.radius() / SaucerSize::Large.radius();
+    let outline = [
+        (-4.0, 0.0),
+        (-2.0, -1.0),
+        (-1.0, -2.5),
+        (1.0, -2.5),
+        (2.0, -1.0),
+        (4.0, 0.0),
+        (2.0, 1.5),
+        (-2.0, 1.5),
+        (-4.0, 0.0),
+    ]
+    .iter()
+    .map(|&(x, y)| (x * scale, y * scale))
+    .collect::<Vec<(f32, f32)>>();
+    world.spawn((
+        components::Position(point.0, point.1, 0.0),
+        components::Velocity(size.speed() * direction, 0.0, 0.0),
+        components::Draw(
+            Color {
+                r: 0.4,
+                g: 0.9,
+                b: 0.4,
+                a: 1.0,
+            },
+            outline,
+        ),
+        components::Collidable(size.radius(), components::CollidableType::SAUCER),
+        components::CollisionLayers::default_for(components::CollidableType::SAUCER),
+        components::Damages::default_for(components::CollidableType::SAUCER),
+        components::HitEffect(
+            Some(Sound::SaucerExplode),
+            Color {
+                r: 0.4,
+                g: 0.9,
+                b: 0.4,
+                a: 1.0,
+            },
+            24,
+            40.0,
+        ),
+        components::PreciseCollision(),
+        components::Saucer(size, size.fire_interval(), distance),
+    ));
+}
+
+impl Game {
+    // Sending saucers across every so often, and having them shoot.
+    pub fn saucer_system(&mut self, delta_time: f32) {
+        let mut saucers_out = 0;
+        let mut shots = Vec::new();
+        let mut leaving = Vec::new();
+        for (id, (position, velocity, saucer)) in self.world.query_mut::<(
+            &components::Position,
+            &mut components::Velocity,
+            &mut components::Saucer,
+        )>() {
+            saucers_out += 1;
+            saucer.2 -= velocity.0.abs() * delta_time;
+            if saucer.2 <= 0.0 {
+                leaving.push(id);
+                continue;
+            }
+            saucer.1 -= delta_time;
+            if saucer.1 <= 0.0 {
+                saucer.1 = saucer.0.fire_interval();
+                shots.push((id, *position, saucer.0));
+                // Changing course a little every shot, up, down or straight on.
+                velocity.1 = ((self.rng.rand() % 3) as f32 - 1.0) * saucer.0.speed() / 2.0;
+            }
+        }
+        // Flown right across, so it's gone, no explosion.
+        for id in leaving {
+            let _ = self.world.despawn(id);
+        }
+
+        // Where the player is, for the small one to aim at, as long as they're actually there.
+        let target = self
+            .world
+            .query_mut::<(&components::Position, &components::Controllable)>()
+            .without::<&components::Hyperspace>()
+            .without::<&components::Respawning>()
+            .into_iter()
+            .next()
+            .map(|(_id, (position, _))| (position.0, position.1));
+
+        for (id, position, size) in shots {
+            let direction = match (size, target) {
+                (SaucerSize::Small, Some(target)) => {
+                    let delta = self.playfield.delta((position.0, position.1), target);
+                    let spread = (SMALL_SAUCER_SPREAD.0
+                        - self.score as f32 * SMALL_SAUCER_SPREAD_PER_POINT)
+                        .max(SMALL_SAUCER_SPREAD.1);
+                    rad2deg(delta.1.atan2(delta.0)) + self.rng.gen_range(-spread, spread)
+                }
+                _ => self.rng.gen_range(0.0, 360.0),
+            };
+            let heading = (deg2rad(direction).cos(), deg2rad(direction).sin());
+            // Starting outside the hull, so it doesn't have to rely on ignoring its own bullets.
+            let offset = size.radius() + 1.5;
+            let spawn_point = self.playfield.wrap((
+                position.0 + heading.0 * offset,
+                position.1 + heading.1 * offset,
+            ));
+            create_bullet(
+                &mut self.world,
+                id,
+                // Bullets are drawn pointing up, so turning them to face the way they're going.
+                (spawn_point.0, spawn_point.1, direction + 90.0),
+                (
+                    heading.0 * SAUCER_BULLET_SPEED,
+                    heading.1 * SAUCER_BULLET_SPEED,
+                ),
+                Color {
+                    r: 0.4,
+                    g: 1.0,
+                    b: 0.4,
+                    a: 1.0,
+                },
+                (self.bullet_lifetime, self.bullet_range),
+            );
+            self.sounds.push(Sound::SaucerShoot);
+        }
+
+        // Only one at a time.
+        if saucers_out > 0 {
+            return;
+        }
+        self.saucer_cooldown -= delta_time;
+        if self.saucer_cooldown <= 0.0 {
+            // Somewhere within a third either side of what the difficulty says.
+            let interval = self.setting(self.difficulty.saucer_interval);
+            self.saucer_cooldown = self
+                .rng
+                .gen_range(interval * 2.0 / 3.0, interval * 4.0 / 3.0);
+            let small_chance = if (self.score as f32) < SMALL_SAUCER_SCORE {
+                0.0
+            } else {
+                (self.score as f32 / (SMALL_SAUCER_SCORE * 5.0)).min(SMALL_SAUCER_MAX_CHANCE)
+            };
+            let size = if self.rng.gen_range(0.0, 1.0) < small_chance {
+                SaucerSize::Small
+            } else {
+                SaucerSize::Large
+            };
+            let height = self
+                .rng
+                .gen_range(self.playfield.min.1, self.playfield.max.1);
+            create_saucer(
+                &mut self.world,
+                &mut self.rng,
+                self.playfield.width(),
+                (self.playfield.min.0, height),
+                size,
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TICK: f32 = 1.0 / 120.0;
+
+    fn saucers(game: &mut Game) -> Vec<Entity> {
+        game.world
+            .query_mut::<&components::Saucer>()
+            .into_iter()
+            .map(|(id, _)| id)
+            .collect()
+    }
+
+    #[test]
+    fn only_one_at_a_time() {
+        let mut game = Game::new(1);
+        game.restart(1);
+        game.saucer_cooldown = 0.0;
+        game.saucer_system(TICK);
+        assert_eq!(saucers(&mut game).len(), 1);
+        game.saucer_cooldown = 0.0;
+        game.saucer_system(TICK);
+        assert_eq!(saucers(&mut game).len(), 1);
+    }
+
+    #[test]
+    fn gone_once_its_flown_across() {
+        let mut game = Game::new(1);
+        game.restart(1);
+        game.saucer_cooldown = 0.0;
+        game.saucer_system(TICK);
+        game.saucer_cooldown = 1000.0;
+        // No score yet, so it's always the big one.
+        let crossing = game.playfield.width() / SaucerSize::Large.speed();
+        for _ in 0..(crossing * 0.9 / TICK) as u32 {
+            game.saucer_system(TICK);
+        }
+        assert_eq!(saucers(&mut game).len(), 1);
+        for _ in 0..(crossing * 0.2 / TICK) as u32 {
+            game.saucer_system(TICK);
+        }
+        assert!(saucers(&mut game).is_empty());
+    }
+
+    // A small saucer at its sharpest taking a bunch of shots, and how far off the ship each one went, in degrees.
+    fn small_saucer_misses(respawning: bool) -> Vec<f32> {
+        let mut game = Game::new(1);
+        game.restart(1);
+        game.score = 100_000;
+        game.saucer_cooldown = 1000.0;
+        let (ship, target) = game
+            .world
+            .query_mut::<(&components::Position, &components::Controllable)>()
+            .into_iter()
+            .next()
+            .map(|(id, (position, _))| (id, (position.0, position.1)))
+            .unwrap();
+        if respawning {
+            game.world
+                .insert_one(ship, components::Respawning(10.0))
+                .unwrap();
+        }
+        let start = (game.playfield.min.0 + 10.0, game.playfield.center().1);
+        create_saucer(
+            &mut game.world,
+            &mut game.rng,
+            1000.0,
+            start,
+            SaucerSize::Small,
+        );
+        let saucer = saucers(&mut game)[0];
+        for _ in 0..20 {
+            game.world.get::<&mut components::Saucer>(saucer).unwrap().1 = 0.0;
+            game.saucer_system(TICK);
+        }
+        let playfield = game.playfield;
+        game.world
+            .query_mut::<(
+                &components::Position,
+                &components::Velocity,
+                &components::Owner,
+            )>()
+            .into_iter()
+            .filter(|(_id, (_, _, owner))| owner.0 == saucer)
+            .map(|(_id, (position, velocity, _))| {
+                let delta = playfield.delta((position.0, position.1), target);
+                let miss = rad2deg(velocity.1.atan2(velocity.0) - delta.1.atan2(delta.0));
+                (miss + 540.0).rem_euclid(360.0) - 180.0
+            })
+            .map(f32::abs)
+            .collect()
+    }
+
+    #[test]
+    fn small_saucers_only_aim_at_ships_that_are_there() {
+        let aimed = small_saucer_misses(false);
+        assert_eq!(aimed.len(), 20);
+        assert!(aimed
+            .iter()
+            .all(|&miss| miss <= SMALL_SAUCER_SPREAD.1 + 0.01));
+        let wild = small_saucer_misses(true);
+        assert_eq!(wild.len(), 20);
+        assert!(wild.iter().any(|&miss| miss > 10.0 * SMALL_SAUCER_SPREAD.1));
+    }
+}
@@ -0,0 +1,162 @@
+use hecs::*;
+
+use crate::components::{self, PowerUpKind};
+use crate::game::{Game, Inputs};
+
+pub const SHIELD_RADIUS: f32 = 4.0; // How far out the bubble goes, which is what gets hit while it's up.
+const SHIELD_DRAIN: f32 = 0.4; // Energy used per second it's held up, out of 1.
+const SHIELD_RECHARGE: f32 = 0.15; // Energy back per second it's down.
+const SHIELD_HIT_COST: f32 = 0.1; // Energy knocked off by anything it stops.
+const SHIELD_MIN_ENERGY: f32 = 0.2; // What it needs to go up again once it's been down, so it can't just flicker.
+
+impl Game {
+    // Putting shields up for anyone holding the key with energy to spare, and charging the rest.
+    pub fn shield_system(&mut self, inputs: &Inputs, delta_time: f32) {
+        for (_id, (shield, power_ups)) in self
+            .world
+            .query_mut::<(&mut components::Shield, Option<&components::PowerUps>)>()
+            .with::<&components::Controllable>()
+            .without::<&components::Hyperspace>()
+            .without::<&components::Respawning>()
+        {
+            // The pickup keeps it up for free while it lasts.
+            if power_ups.is_some_and(|power_ups| power_ups.has(PowerUpKind::Shield)) {
+                shield.1 = true;
+                continue;
+            }
+            let energy_needed = if shield.1 { 0.0 } else { SHIELD_MIN_ENERGY };
+            shield.1 = inputs.shield && shield.0 > energy_needed;
+            shield.0 = if shield.1 {
+                (shield.0 - SHIELD_DRAIN * delta_time).max(0.0)
+            } else {
+                (shield.0 + SHIELD_RECHARGE * delta_time).min(1.0)
+            };
+        }
+    }
+
+    pub fn shielded(&self, entity: Entity) -> bool {
+        self.world
+            .get::<&components::Shield>(entity)
+            .is_ok_and(|shield| shield.1)
+    }
+
+    // Something bounced off, which costs a bit, unless it's running on a pickup.
+    pub fn shield_hit(&mut self, entity: Entity) {
+        let free = self
+            .world
+            .get::<&components::PowerUps>(entity)
+            .is_ok_and(|power_ups| power_ups.has(PowerUpKind::Shield));
+        if let Ok(mut shield) = self.world.get::<&mut components::Shield>(entity) {
+            if !free {
+                shield.0 = (shield.0 - SHIELD_HIT_COST).max(0.0);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::create_asteroid_point;
+
+    const TICK: f32 = 1.0 / 120.0;
+
+    fn ship(game: &mut Game) -> Entity {
+        game.restart(1);
+        game.world
+            .query_mut::<&components::Controllable>()
+            .into_iter()
+            .next()
+            .unwrap()
+            .0
+    }
+
+    fn shield(game: &Game, ship: Entity) -> (f32, bool) {
+        let shield = game.world.get::<&components::Shield>(ship).unwrap();
+        (shield.0, shield.1)
+    }
+
+    #[test]
+    fn holding_it_drains_and_letting_go_charges() {
+        let mut game = Game::new(1);
+        let ship = ship(&mut game);
+        let held = Inputs {
+            shield: true,
+            ..Default::default()
+        };
+        game.shield_system(&held, 1.0);
+        let (energy, up) = shield(&game, ship);
+        assert!(up && (energy - (1.0 - SHIELD_DRAIN)).abs() < 1e-6);
+
+        game.shield_system(&Inputs::default(), 1.0);
+        let (recharged, up) = shield(&game, ship);
+        assert!(!up && (recharged - (energy + SHIELD_RECHARGE)).abs() < 1e-6);
+
+        // Held long enough, it runs dry and drops, and holding on doesn't bring it straight back.
+        for _ in 0..30 {
+            game.shield_system(&held, 0.1);
+        }
+        let (energy, up) = shield(&game, ship);
+        assert!(!up && energy > 0.0 && energy < SHIELD_MIN_ENERGY);
+    }
+
+    #[test]
+    fn needs_a_bit_of_charge_to_go_back_up() {
+        let mut game = Game::new(1);
+        let ship = ship(&mut game);
+        let held = Inputs {
+            shield: true,
+            ..Default::default()
+        };
+        *game.world.get::<&mut components::Shield>(ship).unwrap() =
+            components::Shield(SHIELD_MIN_ENERGY - 0.01, false);
+        game.shield_system(&held, TICK);
+        assert!(!shield(&game, ship).1);
+
+        // Once it's past the line it goes up, and stays up below it while held.
+        *game.world.get::<&mut components::Shield>(ship).unwrap() =
+            components::Shield(SHIELD_MIN_ENERGY + 0.01, false);
+        game.shield_system(&held, TICK);
+        assert!(shield(&game, ship).1);
+        game.shield_system(&held, 0.1);
+        let (energy, up) = shield(&game, ship);
+        assert!(up && energy < SHIELD_MIN_ENERGY);
+    }
+
+    #[test]
+    fn rocks_cost_energy_not_lives() {
+        let mut game = Game::new(1);
+        let ship = ship(&mut game);
+        let here = {
+            let position = game.world.get::<&components::Position>(ship).unwrap();
+            (position.0, position.1)
+        };
+        create_asteroid_point(&mut game.world, &mut game.rng, here, 3.0, 0.0);
+        let held = Inputs {
+            shield: true,
+            ..Default::default()
+        };
+        game.step(&held, TICK);
+        assert_eq!(game.lives, 3);
+        assert!(game.world.get::<&components::Respawning>(ship).is_err());
+        let (energy, up) = shield(&game, ship);
+        assert!(up && energy <= 1.0 - SHIELD_HIT_COST);
+    }
+
+    #[test]
+    fn the_pickup_shield_is_free() {
+        let mut game = Game::new(1);
+        let ship = ship(&mut game);
+        game.world
+            .insert_one(
+                ship,
+                components::PowerUps(vec![(PowerUpKind::Shield, 10.0)]),
+            )
+            .unwrap();
+        *game.world.get::<&mut components::Shield>(ship).unwrap() = components::Shield(0.0, false);
+        // Up without holding anything, or any energy, and hits don't cost.
+        game.shield_system(&Inputs::default(), 1.0);
+        game.shield_hit(ship);
+        assert_eq!(shield(&game, ship), (0.0, true));
+    }
+}
@@ -0,0 +1,98 @@
+use crate::components;
+use crate::playfield::Playfield;
+
+pub const DEFAULT_TICK_RATE: f32 = 120.0; // Ticks per second.
+const MAX_FRAME_TIME: f32 = 0.25; // Any frame longer than this gets cut short, so we don't spiral.
+
+// The accumulator that decides how many simulation ticks a frame gets.
+#[derive(Debug, Clone, Copy)]
+pub struct FixedTimestep {
+    pub tick_rate: f32,
+    accumulator: f32,
+}
+
+impl Default for FixedTimestep {
+    fn default() -> Self {
+        Self::new(DEFAULT_TICK_RATE)
+    }
+}
+
+impl FixedTimestep {
+    pub fn new(tick_rate: f32) -> Self {
+        Self {
+            tick_rate,
+            accumulator: 0.0,
+        }
+    }
+
+    // How long a single tick is, in seconds.
+    pub fn tick_length(&self) -> f32 {
+        1.0 / self.tick_rate
+    }
+
+    // Feeding in the frame time, getting back how many ticks to run.
+    pub fn advance(&mut self, frame_time: f32) -> u32 {
+        self.accumulator += frame_time.min(MAX_FRAME_TIME);
+        let mut ticks = 0;
+        while self.accumulator >= self.tick_length() {
+            self.accumulator -= self.tick_length();
+            ticks += 1;
+        }
+        ticks
+    }
+
+    // How far we are between the last tick and the next one, from 0 to 1.
+    pub fn alpha(&self) -> f32 {
+        (self.accumulator / self.tick_length()).clamp(0.0, 1.0)
+    }
+}
+
+// Blending between where something was last tick and where it is now, the short way across the seam.
+pub fn interpolate_position(
+    last: &components::LastPosition,
+    current: &components::Position,
+    alpha: f32,
+    playfield: &Playfield,
+) -> components::Position {
+    let delta = playfield.delta((last.0, last.1), (current.0, current.1));
+    // Taking the short way around for rotation.
+    let mut rotation = current.2 - last.2;
+    if rotation > 180.0 {
+        rotation -= 360.0;
+    } else if rotation < -180.0 {
+        rotation += 360.0;
+    }
+    let (x, y) = playfield.wrap((last.0 + delta.0 * alpha, last.1 + delta.1 * alpha));
+    components::Position(x, y, last.2 + rotation * alpha)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ticks_pile_up_and_leave_the_rest_for_alpha() {
+        let mut timestep = FixedTimestep::new(8.0);
+        assert_eq!(timestep.advance(0.25), 2);
+        assert_eq!(timestep.alpha(), 0.0);
+        assert_eq!(timestep.advance(0.0625), 0);
+        assert_eq!(timestep.alpha(), 0.5);
+        assert_eq!(timestep.advance(0.0625), 1);
+        assert_eq!(timestep.alpha(), 0.0);
+        // A huge hitch only counts for MAX_FRAME_TIME.
+        assert_eq!(timestep.advance(10.0), 2);
+    }
+
+    #[test]
+    fn interpolation_goes_across_the_seam() {
+        let field = Playfield::new((0.0, 0.0), (100.0, 50.0));
+        let last = components::LastPosition(95.0, 10.0, 350.0);
+        let current = components::Position(5.0, 10.0, 10.0);
+        let quarter = interpolate_position(&last, &current, 0.25, &field);
+        assert_eq!((quarter.0, quarter.1, quarter.2), (97.5, 10.0, 355.0));
+        let half = interpolate_position(&last, &current, 0.5, &field);
+        assert_eq!((half.0, half.1, half.2), (0.0, 10.0, 360.0));
+        let three_quarters = interpolate_position(&last, &current, 0.75, &field);
+        assert_eq!(three_quarters.0, 2.5);
+    }
+}
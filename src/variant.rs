@@ -0,0 +1,314 @@
+use hecs::*;
+use macroquad::prelude::*;
+
+use crate::components::{self, CollidableType};
+use crate::game::{Game, Sound};
+
+const MAGNET_MAX_SPEED: f32 = 20.0; // Magnetic rocks can't be pulled any faster than this.
+const BLAST_DAMAGES: u32 =
+    components::LAYER_PLAYER | components::LAYER_ASTEROID | components::LAYER_SAUCER; // Bullets and pickups ride it out.
+const EXPLOSION_COLOR: Color = Color {
+    r: 1.0,
+    g: 0.6,
+    b: 0.2,
+    a: 1.0,
+};
+
+// A special kind of rock, described by what it gets on top of an ordinary one.
+// Only whole rocks come in special, whatever they break into is just rock.
+#[derive(Debug, Clone, Copy)]
+pub struct AsteroidVariant {
+    pub color: Color,
+    pub health: u32,   // Hits it takes to break.
+    pub splits: u32,   // Pieces it breaks into.
+    pub explodes: f32, // Blast radius when it goes, or 0 if it just breaks.
+    pub magnetic: f32, // How hard it's pulled toward the ship, or 0 if it isn't.
+}
+
+pub const ASTEROID_VARIANTS: [AsteroidVariant; 4] = [
+    // Takes a few hits to crack.
+    AsteroidVariant {
+        color: Color {
+            r: 0.45,
+            g: 0.55,
+            b: 0.75,
+            a: 1.0,
+        },
+        health: 3,
+        splits: 2,
+        explodes: 0.0,
+        magnetic: 0.0,
+    },
+    // Goes up all at once, and takes its neighbours with it.
+    AsteroidVariant {
+        color: Color {
+            r: 1.0,
+            g: 0.45,
+            b: 0.1,
+            a: 1.0,
+        },
+        health: 1,
+        splits: 0,
+        explodes: 15.0,
+        magnetic: 0.0,
+    },
+    // Shatters into three.
+    AsteroidVariant {
+        color: Color {
+            r: 0.5,
+            g: 1.0,
+            b: 0.9,
+            a: 1.0,
+        },
+        health: 1,
+        splits: 3,
+        explodes: 0.0,
+        magnetic: 0.0,
+    },
+    // Drifts after the ship.
+    AsteroidVariant {
+        color: Color {
+            r: 0.85,
+            g: 0.3,
+            b: 0.85,
+            a: 1.0,
+        },
+        health: 1,
+        splits: 2,
+        explodes: 0.0,
+        magnetic: 6.0,
+    },
+];
+
+impl AsteroidVariant {
+    // Turning a plain rock into one of these. Ordinary values are left off, since that's what no component means.
+    pub fn apply(&self, world: &mut World, rock: Entity) {
+        if let Ok(mut draw) = world.get::<&mut components::Draw>(rock) {
+            draw.0 = self.color;
+        }
+        if self.health > 1 {
+            let _ = world.insert_one(rock, components::Health(self.health));
+        }
+        if self.splits != 2 {
+            let _ = world.insert_one(rock, components::Splits(self.splits));
+        }
+        if self.explodes > 0.0 {
+            let _ = world.insert_one(rock, components::Explodes(self.explodes));
+        }
+        if self.magnetic > 0.0 {
+            let _ = world.insert_one(rock, components::Magnetic(self.magnetic));
+        }
+    }
+}
+
+impl Game {
+    // Pulling magnetic rocks toward the ship, if there's one about to be pulled toward.
+    pub fn magnet_system(&mut self, delta_time: f32) {
+        let ship = self
+            .world
+            .query_mut::<(&components::Position, &components::Controllable)>()
+            .without::<&components::Hyperspace>()
+            .without::<&components::Respawning>()
+            .into_iter()
+            .next()
+            .map(|(_id, (position, _))| (position.0, position.1));
+        let Some(ship) = ship else {
+            return;
+        };
+        let playfield = self.playfield;
+        for (_id, (position, velocity, magnetic)) in self.world.query_mut::<(
+            &components::Position,
+            &mut components::Velocity,
+            &components::Magnetic,
+        )>() {
+            let delta = playfield.delta((position.0, position.1), ship);
+            let distance = (delta.0 * delta.0 + delta.1 * delta.1).sqrt();
+            if distance <= 0.0 {
+                continue;
+            }
+            velocity.0 += delta.0 / distance * magnetic.0 * delta_time;
+            velocity.1 += delta.1 / distance * magnetic.0 * delta_time;
+            let speed = (velocity.0 * velocity.0 + velocity.1 * velocity.1).sqrt();
+            if speed > MAGNET_MAX_SPEED {
+                velocity.0 *= MAGNET_MAX_SPEED / speed;
+                velocity.1 *= MAGNET_MAX_SPEED / speed;
+            }
+        }
+    }
+
+    // Setting off anything explosive that got destroyed this tick, and whatever that sets off in turn.
+    // Blasts only pay out if the player's shot set the whole thing off, and there's a run going to pay into.
+    pub fn explosion_system(&mut self) {
+        let sides = self.collision_sides();
+        let mut blasts = self
+            .entities_to_destroy
+            .iter()
+            .copied()
+            .filter(|entity| self.world.get::<&components::Explodes>(*entity).is_ok())
+            .map(|rock| {
+                let shot_by_player = sides.iter().any(|(this, _, other, other_kind)| {
+                    *this == rock
+                        && *other_kind == CollidableType::BULLET
+                        && self.fired_by_player(*other)
+                });
+                (rock, shot_by_player)
+            })
+            .collect::<Vec<_>>();
+        let mut gone_off = Vec::new();
+        while let Some((rock, shot_by_player)) = blasts.pop() {
+            if gone_off.contains(&rock) {
+                continue;
+            }
+            gone_off.push(rock);
+            let (Some(center), Ok(radius)) = (
+                self.position_of(rock),
+                self.world
+                    .get::<&components::Explodes>(rock)
+                    .map(|explodes| explodes.0),
+            ) else {
+                continue;
+            };
+            self.explosion_particles((center.0, center.1), radius);
+            self.sounds.push(Sound::AsteroidExplode);
+
+            // Everything close enough to get caught in it, the player included.
+            let playfield = self.playfield;
+            let caught = self
+                .world
+                .query_mut::<(&components::Position, &components::Collidable)>()
+                .without::<&components::Hyperspace>()
+                .without::<&components::Respawning>()
+                .without::<&components::Invulnerable>()
+                .into_iter()
+                .filter(|(id, (position, collidable))| {
+                    let reach = radius + collidable.0;
+                    *id != rock
+                        && playfield
+                            .distance_squared((center.0, center.1), (position.0, position.1))
+                            < reach * reach
+                })
+                .map(|(id, (_, collidable))| (id, collidable.1))
+                .collect::<Vec<_>>();
+            for (id, kind) in caught {
+                if self.entities_to_destroy.contains(&id)
+                    || self.layers_of(id).0 & BLAST_DAMAGES == 0
+                    || !self.hurt(id)
+                {
+                    continue;
+                }
+                if self.layers_of(id).0 & components::LAYER_ASTEROID == 0 {
+                    if let Ok(effect) = self.world.get::<&components::HitEffect>(id) {
+                        self.sounds.extend(effect.0);
+                    }
+                    continue;
+                }
+                self.split_asteroid(id);
+                if shot_by_player && self.lives > 0 {
+                    if let Some(points) = self.points_for(id, kind) {
+                        let points = points * self.combo_hit();
+                        self.add_score(points);
+                        if let Some(position) = self.position_of(id) {
+                            self.score_popup((position.0, position.1), points);
+                        }
+                    }
+                }
+                if self.world.get::<&components::Explodes>(id).is_ok() {
+                    blasts.push((id, shot_by_player));
+                }
+            }
+        }
+    }
+
+    fn explosion_particles(&mut self, point: (f32, f32), radius: f32) {
+        self.particles.create_particle(
+            32,
+            point,
+            (0.0, 0.0),
+            0.92,
+            0.8,
+            EXPLOSION_COLOR,
+            0.5,
+            (radius / 4.0, radius / 4.0),
+            (radius * 4.0, radius * 4.0),
+            0.3,
+            0.15,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::collision::CollisionEvent;
+    use crate::game::{create_asteroid_point, create_bullet};
+
+    // An explosive rock with a plain one right next to it, just shot by whoever's bullets the owner fires.
+    fn blast(game: &mut Game, owner: Entity) {
+        let center = game.playfield.center();
+        let explosive = create_asteroid_point(&mut game.world, &mut game.rng, center, 8.0, 0.0);
+        ASTEROID_VARIANTS[1].apply(&mut game.world, explosive);
+        create_asteroid_point(
+            &mut game.world,
+            &mut game.rng,
+            (center.0 + 10.0, center.1),
+            8.0,
+            0.0,
+        );
+        let bullet = create_bullet(
+            &mut game.world,
+            owner,
+            (center.0, center.1, 0.0),
+            (0.0, 0.0),
+            WHITE,
+            (1.0, 1.0),
+        );
+        game.collision_events.push(CollisionEvent {
+            a: explosive,
+            b: bullet,
+            kind_a: CollidableType::ASTEROID,
+            kind_b: CollidableType::BULLET,
+            point: center,
+        });
+        game.entities_to_destroy.push(explosive);
+        game.explosion_system();
+    }
+
+    fn player(game: &Game) -> Entity {
+        game.world
+            .query::<&components::Controllable>()
+            .iter()
+            .next()
+            .unwrap()
+            .0
+    }
+
+    #[test]
+    fn player_blasts_score_through_the_combo() {
+        let mut game = Game::new(1);
+        game.restart(1);
+        let ship = player(&game);
+        blast(&mut game, ship);
+        assert!(game.score > 0);
+        assert_eq!(game.combo.chain, 1);
+    }
+
+    #[test]
+    fn saucer_blasts_dont_score() {
+        let mut game = Game::new(1);
+        game.restart(1);
+        let saucer = game.world.spawn((components::Position(0.0, 0.0, 0.0),));
+        blast(&mut game, saucer);
+        assert_eq!(game.score, 0);
+        assert_eq!(game.combo.chain, 0);
+    }
+
+    #[test]
+    fn blasts_dont_score_without_a_run() {
+        let mut game = Game::new(1);
+        game.restart(1);
+        game.lives = 0;
+        let ship = player(&game);
+        blast(&mut game, ship);
+        assert_eq!(game.score, 0);
+    }
+}
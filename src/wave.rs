@@ -0,0 +1,138 @@
+use crate::components::{self, CollidableType};
+use crate::game::{create_asteroid_point, edge_point, Game};
+use crate::variant::ASTEROID_VARIANTS;
+
+pub const WAVE_DELAY: f32 = 2.0; // Seconds of breather between clearing a wave and the next one.
+const SAFE_DISTANCE: f32 = 30.0; // How close a new rock's allowed to start to the ship.
+const SPAWN_ATTEMPTS: u32 = 16; // How many spots to try before giving up on keeping clear of the ship.
+
+impl Game {
+    // Waiting for the field to clear, then sending in the next wave.
+    pub fn wave_system(&mut self, delta_time: f32) {
+        let rocks_left = self
+            .world
+            .query_mut::<&components::Collidable>()
+            .into_iter()
+            .any(|(_id, collidable)| collidable.1 == CollidableType::ASTEROID);
+        if rocks_left || !self.asteroids_to_create.is_empty() {
+            return;
+        }
+
+        self.wave_delay -= delta_time;
+        if self.wave_delay > 0.0 {
+            return;
+        }
+        self.wave += 1;
+        self.wave_delay = WAVE_DELAY;
+
+        let ship = self
+            .world
+            .query_mut::<(&components::Position, &components::Controllable)>()
+            .into_iter()
+            .next()
+            .map(|(_id, (position, _))| (position.0, position.1));
+        let count = self.setting(self.difficulty.wave_asteroids).round() as u32;
+        let size = self.setting(self.difficulty.asteroid_size);
+        let speed = self.setting(self.difficulty.asteroid_speed);
+        let special_chance = self.setting(self.difficulty.special_asteroids);
+        for _ in 0..count {
+            // Trying spots around the edge until one's far enough from the ship.
+            let mut point = edge_point(&mut self.rng, &self.playfield);
+            for _ in 1..SPAWN_ATTEMPTS {
+                match ship {
+                    Some(ship)
+                        if self.playfield.distance_squared(ship, point)
+                            < SAFE_DISTANCE * SAFE_DISTANCE =>
+                    {
+                        point = edge_point(&mut self.rng, &self.playfield)
+                    }
+                    _ => break,
+                }
+            }
+            let rock = create_asteroid_point(&mut self.world, &mut self.rng, point, size, speed);
+            // Every so often, something other than plain rock.
+            if self.rng.unit() < special_chance as f64 {
+                let variant = ASTEROID_VARIANTS[self.rng.rand() as usize % ASTEROID_VARIANTS.len()];
+                variant.apply(&mut self.world, rock);
+            }
+        }
+    }
+
+    // Whether we're between waves, for the banner.
+    pub fn wave_incoming(&self) -> bool {
+        self.wave_delay < WAVE_DELAY
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TICK: f32 = 1.0 / 120.0;
+
+    fn rocks(game: &mut Game) -> Vec<(hecs::Entity, (f32, f32))> {
+        game.world
+            .query_mut::<(&components::Position, &components::Collidable)>()
+            .into_iter()
+            .filter(|(_id, (_, collidable))| collidable.1 == CollidableType::ASTEROID)
+            .map(|(id, (position, _))| (id, (position.0, position.1)))
+            .collect()
+    }
+
+    fn wait(game: &mut Game, seconds: f32) {
+        for _ in 0..(seconds / TICK).round() as u32 {
+            game.wave_system(TICK);
+        }
+    }
+
+    #[test]
+    fn next_wave_waits_for_a_clear_field_and_a_breather() {
+        let mut game = Game::new(1);
+        game.restart(1);
+        wait(&mut game, WAVE_DELAY - 0.1);
+        assert_eq!(game.wave, 0);
+        wait(&mut game, 0.2);
+        assert_eq!(game.wave, 1);
+        assert!(!rocks(&mut game).is_empty());
+
+        // However long the rocks hang about, nothing new comes in.
+        wait(&mut game, 30.0);
+        assert_eq!(game.wave, 1);
+
+        for (rock, _) in rocks(&mut game) {
+            game.world.despawn(rock).unwrap();
+        }
+        wait(&mut game, WAVE_DELAY - 0.1);
+        assert_eq!(game.wave, 1);
+        assert!(game.wave_incoming());
+        wait(&mut game, 0.2);
+        assert_eq!(game.wave, 2);
+    }
+
+    #[test]
+    fn rocks_keep_clear_of_the_ship() {
+        for seed in 0..32 {
+            let mut game = Game::new(seed);
+            game.restart(seed);
+            let ship = game
+                .world
+                .query_mut::<(&components::Position, &components::Controllable)>()
+                .into_iter()
+                .next()
+                .map(|(_id, (position, _))| (position.0, position.1))
+                .unwrap();
+            // A few waves' worth, clearing the field between them.
+            for _ in 0..3 {
+                wait(&mut game, WAVE_DELAY + 0.1);
+                for (rock, point) in rocks(&mut game) {
+                    assert!(
+                        game.playfield.distance_squared(ship, point)
+                            >= SAFE_DISTANCE * SAFE_DISTANCE
+                    );
+                    game.world.despawn(rock).unwrap();
+                }
+            }
+            assert_eq!(game.wave, 3);
+        }
+    }
+}